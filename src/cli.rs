@@ -1,7 +1,12 @@
 use {
-    crate::{gui::Ui, portal::Portal},
+    crate::{
+        config::{self, ConfigHandle},
+        gui::Ui,
+        portal::Portal,
+    },
     clap::Parser,
     error_reporter::Report,
+    std::path::PathBuf,
 };
 
 /// The xdg-desktop-portal-gtk4 portal.
@@ -10,12 +15,21 @@ struct Cli {
     /// Replace the portal if it is already running.
     #[clap(long)]
     pub replace: bool,
+    /// The configuration file to use.
+    ///
+    /// Defaults to `$XDG_CONFIG_HOME/xdg-desktop-portal-gtk4/config`. The file is reloaded
+    /// when it changes and when the portal receives SIGHUP.
+    #[clap(long)]
+    pub config: Option<PathBuf>,
 }
 
 pub fn main() {
     let args = Cli::parse();
     let ui = Ui::new();
-    let _portal = match Portal::create(ui.proxy(), args.replace) {
+    let config = ConfigHandle::load(args.config.unwrap_or_else(config::default_path));
+    config.reload_on_sighup();
+    let _monitors = config.reload_on_change();
+    let _portal = match Portal::create(ui.proxy(), &config, args.replace) {
         Ok(p) => p,
         Err(e) => {
             log::error!("Could not create the portal: {}", Report::new(e));
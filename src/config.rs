@@ -0,0 +1,401 @@
+use {
+    crate::config::app_rules::AppRules,
+    async_channel::{Receiver, Sender},
+    error_reporter::Report,
+    gtk4::{
+        gio::{
+            self,
+            prelude::{FileExt, FileMonitorExt},
+            Cancellable, FileMonitor, FileMonitorEvent, FileMonitorFlags,
+        },
+        glib::{self, ControlFlow, FileError, KeyFile, KeyFileError, KeyFileFlags},
+    },
+    std::{
+        ffi::OsString,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex, RwLock},
+    },
+};
+
+pub mod app_rules;
+
+const DEFAULT_CONFLICT_PATTERN: &str = "{name} ({n}){ext}";
+
+/// The portal-wide configuration.
+///
+/// The configuration is stored as a key file. All keys are optional:
+///
+/// ```ini
+/// [general]
+/// modal=true
+///
+/// [file-chooser]
+/// read-only=true
+/// record-recent=true
+/// conflict-pattern={name} ({n}){ext}
+///
+/// [email]
+/// command=thunderbird -compose
+///
+/// [wallpaper]
+/// background-command=swww img %f
+///
+/// [dynamic-launcher]
+/// install-token-apps=org.example.Games;
+///
+/// [lockdown]
+/// disable-printing=false
+/// disable-save-to-disk=false
+/// disable-application-handlers=false
+/// disable-location=false
+/// disable-camera=false
+/// disable-microphone=false
+/// disable-sound-output=false
+/// ```
+///
+/// Per-application overrides are stored in a separate file called `apps` next to the
+/// configuration file. See [`AppRules`].
+#[derive(Debug)]
+pub struct Config {
+    /// Whether dialogs are modal if the client does not specify it.
+    pub modal: bool,
+    pub file_chooser: FileChooserConfig,
+    pub email: EmailConfig,
+    pub wallpaper: WallpaperConfig,
+    pub dynamic_launcher: DynamicLauncherConfig,
+    pub lockdown: LockdownConfig,
+    pub apps: AppRules,
+}
+
+#[derive(Debug)]
+pub struct FileChooserConfig {
+    /// Whether the "Open files read-only" choice is enabled by default.
+    pub read_only: bool,
+    /// Whether selected files are added to the recently used files.
+    pub record_recent: bool,
+    /// The pattern used by `SaveFiles` to rename files that already exist.
+    ///
+    /// `{name}` is replaced by the file name up to the first `.`, `{ext}` by the rest of the
+    /// file name including the `.`, and `{n}` by a counter starting at 1.
+    conflict_pattern: String,
+}
+
+#[derive(Debug, Default)]
+pub struct EmailConfig {
+    /// The command that starts the mail client, split into arguments.
+    ///
+    /// If this is not set, the default handler of `mailto` URIs is used.
+    pub command: Option<Vec<OsString>>,
+}
+
+#[derive(Debug, Default)]
+pub struct WallpaperConfig {
+    /// The command that sets the background, split into arguments.
+    ///
+    /// `%f` is replaced by the path of the image.
+    pub background_command: Option<Vec<OsString>>,
+    /// The command that sets the lock screen background.
+    pub lockscreen_command: Option<Vec<OsString>>,
+}
+
+#[derive(Debug, Default)]
+pub struct DynamicLauncherConfig {
+    /// The apps that may install launchers without showing a dialog.
+    pub install_token_apps: Vec<String>,
+}
+
+/// Features that are disabled, e.g. on kiosk machines.
+///
+/// These are published by the Lockdown portal. Saving to disk is also refused by the
+/// file chooser.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LockdownConfig {
+    pub disable_printing: bool,
+    pub disable_save_to_disk: bool,
+    pub disable_application_handlers: bool,
+    pub disable_location: bool,
+    pub disable_camera: bool,
+    pub disable_microphone: bool,
+    pub disable_sound_output: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            modal: true,
+            file_chooser: FileChooserConfig {
+                read_only: true,
+                record_recent: true,
+                conflict_pattern: DEFAULT_CONFLICT_PATTERN.to_string(),
+            },
+            email: EmailConfig::default(),
+            wallpaper: WallpaperConfig::default(),
+            dynamic_launcher: DynamicLauncherConfig::default(),
+            lockdown: LockdownConfig::default(),
+            apps: AppRules::default(),
+        }
+    }
+}
+
+impl FileChooserConfig {
+    /// Returns the `n`th alternative name for a file that already exists.
+    pub fn conflict_name(&self, name: &str, n: u64, ext: &str) -> String {
+        let mut res = String::new();
+        let mut rest = self.conflict_pattern.as_str();
+        while let Some(c) = rest.chars().next() {
+            if let Some(r) = rest.strip_prefix("{name}") {
+                res.push_str(name);
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix("{n}") {
+                res.push_str(&n.to_string());
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix("{ext}") {
+                res.push_str(ext);
+                rest = r;
+            } else {
+                res.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        res
+    }
+}
+
+impl Config {
+    fn parse(file: &KeyFile) -> Self {
+        let mut config = Self::default();
+        if let Some(v) = boolean(file, "general", "modal") {
+            config.modal = v;
+        }
+        let fc = &mut config.file_chooser;
+        if let Some(v) = boolean(file, "file-chooser", "read-only") {
+            fc.read_only = v;
+        }
+        if let Some(v) = boolean(file, "file-chooser", "record-recent") {
+            fc.record_recent = v;
+        }
+        if let Some(v) = string(file, "file-chooser", "conflict-pattern") {
+            if v.contains("{n}") && !v.contains('/') {
+                fc.conflict_pattern = v;
+            } else {
+                log::warn!(
+                    "Ignoring file-chooser.conflict-pattern `{v}`: \
+                     The pattern must contain `{{n}}` and must not contain `/`"
+                );
+            }
+        }
+        config.email.command = command(file, "email", "command");
+        let wallpaper = &mut config.wallpaper;
+        wallpaper.background_command = command(file, "wallpaper", "background-command");
+        wallpaper.lockscreen_command = command(file, "wallpaper", "lockscreen-command");
+        if let Some(v) = string_list(file, "dynamic-launcher", "install-token-apps") {
+            config.dynamic_launcher.install_token_apps = v;
+        }
+        let lockdown = &mut config.lockdown;
+        for (key, value) in [
+            ("disable-printing", &mut lockdown.disable_printing),
+            ("disable-save-to-disk", &mut lockdown.disable_save_to_disk),
+            (
+                "disable-application-handlers",
+                &mut lockdown.disable_application_handlers,
+            ),
+            ("disable-location", &mut lockdown.disable_location),
+            ("disable-camera", &mut lockdown.disable_camera),
+            ("disable-microphone", &mut lockdown.disable_microphone),
+            ("disable-sound-output", &mut lockdown.disable_sound_output),
+        ] {
+            if let Some(v) = boolean(file, "lockdown", key) {
+                *value = v;
+            }
+        }
+        config
+    }
+}
+
+fn boolean(file: &KeyFile, group: &str, key: &str) -> Option<bool> {
+    get(file.boolean(group, key), group, key)
+}
+
+fn string(file: &KeyFile, group: &str, key: &str) -> Option<String> {
+    get(file.string(group, key), group, key).map(|s| s.to_string())
+}
+
+/// Returns a command line split into arguments.
+fn command(file: &KeyFile, group: &str, key: &str) -> Option<Vec<OsString>> {
+    let v = string(file, group, key)?;
+    match glib::shell_parse_argv(&v) {
+        Ok(argv) => Some(argv),
+        Err(e) => {
+            log::warn!("Ignoring {group}.{key} `{v}`: {}", Report::new(e));
+            None
+        }
+    }
+}
+
+fn string_list(file: &KeyFile, group: &str, key: &str) -> Option<Vec<String>> {
+    get(file.string_list(group, key), group, key).map(|l| l.iter().map(|s| s.to_string()).collect())
+}
+
+/// Returns the value of a key or `None` if the key does not exist or is invalid.
+fn get<T>(res: Result<T, glib::Error>, group: &str, key: &str) -> Option<T> {
+    match res {
+        Ok(v) => Some(v),
+        Err(e)
+            if e.matches(KeyFileError::GroupNotFound) || e.matches(KeyFileError::KeyNotFound) =>
+        {
+            None
+        }
+        Err(e) => {
+            log::warn!(
+                "Ignoring invalid value of {group}.{key}: {}",
+                Report::new(e)
+            );
+            None
+        }
+    }
+}
+
+/// Returns the default location of the configuration file.
+pub fn default_path() -> PathBuf {
+    glib::user_config_dir()
+        .join("xdg-desktop-portal-gtk4")
+        .join("config")
+}
+
+/// Returns the location of the key file with settings values.
+///
+/// See [`UiProxy::watch_settings`](crate::gui::UiProxy::watch_settings).
+pub fn settings_path() -> PathBuf {
+    glib::user_config_dir()
+        .join("xdg-desktop-portal-gtk4")
+        .join("settings")
+}
+
+/// A handle to the current configuration.
+///
+/// The configuration can be reloaded at runtime. Operations that are already running
+/// continue to use the configuration that was current when they started.
+#[derive(Clone)]
+pub struct ConfigHandle {
+    path: Option<Arc<PathBuf>>,
+    current: Arc<RwLock<Arc<Config>>>,
+    watchers: Arc<Mutex<Vec<Sender<Arc<Config>>>>>,
+}
+
+impl ConfigHandle {
+    pub fn load(path: PathBuf) -> Self {
+        let config = read(&path).unwrap_or_default();
+        Self {
+            path: Some(Arc::new(path)),
+            current: Arc::new(RwLock::new(Arc::new(config))),
+            watchers: Default::default(),
+        }
+    }
+
+    /// Creates a handle that is not backed by a file.
+    #[cfg(test)]
+    pub fn fixed(config: Config) -> Self {
+        Self {
+            path: None,
+            current: Arc::new(RwLock::new(Arc::new(config))),
+            watchers: Default::default(),
+        }
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Reloads the configuration file.
+    ///
+    /// If the file cannot be parsed, the previous configuration remains active.
+    pub fn reload(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Some(config) = read(path) {
+            log::info!("Reloaded configuration from {}", path.display());
+            let config = Arc::new(config);
+            *self.current.write().unwrap() = config.clone();
+            self.watchers
+                .lock()
+                .unwrap()
+                .retain(|w| w.try_send(config.clone()).is_ok());
+        }
+    }
+
+    /// Returns a channel that receives the configuration whenever it has been reloaded.
+    pub fn watch(&self) -> Receiver<Arc<Config>> {
+        let (send, recv) = async_channel::unbounded();
+        self.watchers.lock().unwrap().push(send);
+        recv
+    }
+
+    /// Reloads the configuration whenever the process receives SIGHUP.
+    ///
+    /// The signal is handled by the default main context.
+    pub fn reload_on_sighup(&self) {
+        let slf = self.clone();
+        glib::unix_signal_add(libc::SIGHUP, move || {
+            slf.reload();
+            ControlFlow::Continue
+        });
+    }
+
+    /// Reloads the configuration whenever the configuration file or the `apps` file
+    /// next to it changes.
+    ///
+    /// The changes are reported on the default main context for as long as the returned
+    /// monitors are alive.
+    pub fn reload_on_change(&self) -> Vec<FileMonitor> {
+        let Some(path) = &self.path else {
+            return vec![];
+        };
+        let mut monitors = vec![];
+        for path in [path.to_path_buf(), path.with_file_name("apps")] {
+            match gio::File::for_path(&path).monitor_file(FileMonitorFlags::NONE, Cancellable::NONE)
+            {
+                Ok(monitor) => {
+                    let slf = self.clone();
+                    monitor.connect_changed(move |_, _, _, event| {
+                        if matches!(
+                            event,
+                            FileMonitorEvent::ChangesDoneHint
+                                | FileMonitorEvent::Created
+                                | FileMonitorEvent::Deleted
+                        ) {
+                            slf.reload();
+                        }
+                    });
+                    monitors.push(monitor);
+                }
+                Err(e) => log::warn!("Could not monitor {}: {}", path.display(), Report::new(e)),
+            }
+        }
+        monitors
+    }
+}
+
+fn read(path: &Path) -> Option<Config> {
+    let mut config = match load(path)? {
+        Some(file) => Config::parse(&file),
+        None => Config::default(),
+    };
+    if let Some(file) = load(&path.with_file_name("apps"))? {
+        config.apps = AppRules::parse(&file);
+    }
+    Some(config)
+}
+
+/// Loads a key file or returns `Some(None)` if the file does not exist.
+fn load(path: &Path) -> Option<Option<KeyFile>> {
+    let file = KeyFile::new();
+    match file.load_from_file(path, KeyFileFlags::NONE) {
+        Ok(()) => Some(Some(file)),
+        Err(e) if e.matches(FileError::Noent) => Some(None),
+        Err(e) => {
+            log::error!("Could not load {}: {}", path.display(), Report::new(e));
+            None
+        }
+    }
+}
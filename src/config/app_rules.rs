@@ -0,0 +1,212 @@
+use {
+    crate::config::{boolean, string, string_list},
+    gtk4::glib::KeyFile,
+};
+
+/// Per-application overrides.
+///
+/// The rules are stored as a key file. The name of each group is a glob that is matched
+/// against the app id. `*` matches any sequence of characters and `?` matches any single
+/// character. All keys are optional:
+///
+/// ```ini
+/// [org.mozilla.*]
+/// current-folder=/home/user/Downloads
+/// read-only=true
+/// record-recent=false
+/// filters=Images;PDF
+/// ```
+///
+/// If multiple groups match an app id, later groups take precedence.
+#[derive(Debug, Default)]
+pub struct AppRules {
+    rules: Vec<AppRule>,
+}
+
+#[derive(Debug)]
+struct AppRule {
+    pattern: String,
+    policy: AppPolicy,
+}
+
+/// The overrides that apply to a single application.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AppPolicy {
+    /// The folder that file choosers start in, regardless of what the application asks for.
+    pub current_folder: Option<String>,
+    /// If set, files are always opened read-only or writable and the user is not given
+    /// a choice.
+    pub read_only: Option<bool>,
+    /// Whether selected files are added to the recently used files.
+    pub record_recent: Option<bool>,
+    /// The names of the filters of the application that are offered to the user.
+    pub filters: Option<Vec<String>>,
+}
+
+impl AppRules {
+    pub(super) fn parse(file: &KeyFile) -> Self {
+        let mut rules = vec![];
+        for group in file.groups().iter() {
+            let group = group.as_str();
+            rules.push(AppRule {
+                pattern: group.to_string(),
+                policy: AppPolicy {
+                    current_folder: string(file, group, "current-folder"),
+                    read_only: boolean(file, group, "read-only"),
+                    record_recent: boolean(file, group, "record-recent"),
+                    filters: string_list(file, group, "filters"),
+                },
+            });
+        }
+        Self { rules }
+    }
+
+    /// Parses rules from the contents of a key file.
+    #[cfg(test)]
+    pub fn from_data(data: &str) -> Self {
+        use gtk4::glib::KeyFileFlags;
+
+        let file = KeyFile::new();
+        file.load_from_data(data, KeyFileFlags::NONE).unwrap();
+        Self::parse(&file)
+    }
+
+    /// Returns the merged policy of all rules matching the app id.
+    pub fn policy(&self, app_id: &str) -> AppPolicy {
+        let mut res = AppPolicy::default();
+        for rule in &self.rules {
+            if !glob_matches(&rule.pattern, app_id) {
+                continue;
+            }
+            let policy = &rule.policy;
+            macro_rules! merge {
+                ($($field:ident),*) => {
+                    $(
+                        if policy.$field.is_some() {
+                            res.$field.clone_from(&policy.$field);
+                        }
+                    )*
+                };
+            }
+            merge!(current_folder, read_only, record_recent, filters);
+        }
+        res
+    }
+}
+
+impl AppPolicy {
+    /// Returns whether the filter may be offered to the user.
+    pub fn allows_filter(&self, name: &str) -> bool {
+        match &self.filters {
+            Some(filters) => filters.iter().any(|f| f == name),
+            None => true,
+        }
+    }
+}
+
+/// Matches `s` against a glob in which `*` matches any sequence of characters and `?`
+/// matches any single character.
+fn glob_matches(pattern: &str, s: &str) -> bool {
+    let pattern: Vec<_> = pattern.chars().collect();
+    let s: Vec<_> = s.chars().collect();
+    let (mut p, mut i) = (0, 0);
+    let mut backtrack = None;
+    while i < s.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, i));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == s[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bi)) => {
+                    backtrack = Some((bp, bi + 1));
+                    p = bp + 1;
+                    i = bi + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs() {
+        for (pattern, app_id) in [
+            ("org.example.App", "org.example.App"),
+            ("*", ""),
+            ("*", "org.example.App"),
+            ("org.example.*", "org.example.App"),
+            ("org.example.*", "org.example."),
+            ("*.App", "org.example.App"),
+            ("org.*.App", "org.example.App"),
+            ("org.*e.*", "org.example.example.App"),
+            ("org.example.Ap?", "org.example.App"),
+            ("**App", "org.example.App"),
+        ] {
+            assert!(glob_matches(pattern, app_id), "{pattern} {app_id}");
+        }
+        for (pattern, app_id) in [
+            ("org.example.App", "org.example.App2"),
+            ("org.example.App", "org.example.Ap"),
+            ("org.example.*", "org.example"),
+            ("*.App", "org.example.App2"),
+            ("org.example.Ap?", "org.example.Ap"),
+            ("?", ""),
+        ] {
+            assert!(!glob_matches(pattern, app_id), "{pattern} {app_id}");
+        }
+    }
+
+    #[test]
+    fn later_rules_take_precedence() {
+        let rules = AppRules::from_data(
+            "[org.example.*]\n\
+             current-folder=/srv/shared\n\
+             read-only=true\n\
+             filters=Images;PDF\n\
+             [org.example.Editor]\n\
+             read-only=false\n\
+             record-recent=false\n",
+        );
+        assert_eq!(
+            rules.policy("org.example.Editor"),
+            AppPolicy {
+                current_folder: Some("/srv/shared".to_string()),
+                read_only: Some(false),
+                record_recent: Some(false),
+                filters: Some(vec!["Images".to_string(), "PDF".to_string()]),
+            },
+        );
+        assert_eq!(rules.policy("org.example.Viewer").read_only, Some(true));
+        assert_eq!(rules.policy("org.other.App"), AppPolicy::default());
+    }
+
+    #[test]
+    fn invalid_values_are_ignored() {
+        let rules = AppRules::from_data("[*]\nread-only=maybe\nrecord-recent=false\n");
+        assert_eq!(
+            rules.policy("org.example.App"),
+            AppPolicy {
+                record_recent: Some(false),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn filters() {
+        let policy = AppRules::from_data("[*]\nfilters=Images\n").policy("org.example.App");
+        assert!(policy.allows_filter("Images"));
+        assert!(!policy.allows_filter("PDF"));
+        assert!(AppPolicy::default().allows_filter("PDF"));
+    }
+}
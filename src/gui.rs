@@ -1,9 +1,33 @@
-use gtk4::{
-    glib,
-    glib::{MainContext, MainLoop},
+use {
+    crate::utils::cancellation::Cancellation,
+    async_channel::Sender,
+    futures_util::{future::BoxFuture, select, FutureExt},
+    gdk4_wayland::WaylandToplevel,
+    gdk4_x11::{X11Display, X11Surface},
+    gtk4::{
+        gio::{prelude::AppInfoExt, DesktopAppInfo},
+        glib::{self, MainContext, MainLoop, Propagation},
+        prelude::{ButtonExt, Cast, GtkWindowExt, IsA, NativeExt, SurfaceExt, WidgetExt},
+        Button, HeaderBar, Justification, Label, NamedAction, Orientation, Shortcut,
+        ShortcutController, ShortcutTrigger, Widget, Window,
+    },
+    std::{cell::RefCell, rc::Rc},
+    thiserror::Error,
 };
 
+pub mod access;
+pub mod account;
+pub mod app_chooser;
+pub mod background;
+pub mod choices;
+pub mod color_picker;
+pub mod dynamic_launcher;
+pub mod email;
 pub mod file_chooser;
+pub mod print;
+pub mod settings;
+pub mod usb;
+pub mod wallpaper;
 
 pub struct Ui {
     main_loop: MainLoop,
@@ -41,3 +65,260 @@ impl Ui {
 pub struct UiProxy {
     context: MainContext,
 }
+
+#[cfg(test)]
+impl UiProxy {
+    /// Creates a proxy whose main context is never iterated.
+    ///
+    /// Dialogs started through this proxy are never shown and only complete when they
+    /// are cancelled.
+    pub fn detached() -> Self {
+        Self {
+            context: MainContext::new(),
+        }
+    }
+}
+
+/// Makes the window transient for the window identified by `parent_window`.
+///
+/// `parent_window` is a window identifier as passed by xdg-desktop-portal, i.e. either
+/// `wayland:<exported handle>` or `x11:<hexadecimal XID>`. Other identifiers are ignored.
+/// This realizes the window.
+fn set_parent_window(window: &impl IsA<Window>, parent_window: &str) {
+    let window = window.upcast_ref::<Window>();
+    window.upcast_ref::<Widget>().realize();
+    let Some(surface) = window.surface() else {
+        return;
+    };
+    if let Some(parent) = parent_window.strip_prefix("wayland:") {
+        if let Some(toplevel) = surface.downcast_ref::<WaylandToplevel>() {
+            toplevel.set_transient_for_exported(parent);
+        }
+    } else if let Some(parent) = parent_window.strip_prefix("x11:") {
+        let Some(xid) = parse_xid(parent) else {
+            log::warn!("Invalid X11 window identifier {parent}");
+            return;
+        };
+        let Some(surface) = surface.downcast_ref::<X11Surface>() else {
+            return;
+        };
+        let Some(display) = surface.display().downcast_ref::<X11Display>().cloned() else {
+            return;
+        };
+        unsafe {
+            x11::xlib::XSetTransientForHint(display.xdisplay(), surface.xid(), xid);
+        }
+    }
+}
+
+/// Parses the hexadecimal window ID of an `x11:` handle, with or without a `0x` prefix.
+fn parse_xid(s: &str) -> Option<u64> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u64::from_str_radix(digits, 16).ok()
+}
+
+#[derive(Debug, Error)]
+pub enum DialogError {
+    #[error("Operation could not be started")]
+    Closed,
+    #[error("Operation was rejected")]
+    Rejected,
+    #[error("Operation was cancelled")]
+    Cancelled,
+}
+
+/// The window of a dialog shown by [`run_dialog`].
+pub struct DialogOptions {
+    pub title: String,
+    /// The mnemonic label of the button that rejects the dialog.
+    pub cancel_label: String,
+    /// The mnemonic label of the button that accepts the dialog.
+    pub accept_label: String,
+    pub modal: bool,
+    pub default_size: (i32, i32),
+    pub parent_window: String,
+}
+
+/// The content of a dialog with a cancel and an accept button.
+pub trait DialogUi: Send + 'static {
+    type Output: Send + 'static;
+
+    fn options(&self) -> DialogOptions;
+
+    /// Creates the content of the dialog. This is called on the main loop.
+    ///
+    /// The content completes the dialog with [`Dialog::accept_with`].
+    fn build(self, dialog: &Dialog<Self::Output>) -> Widget;
+}
+
+/// A frontend that shows dialogs of type `U`.
+pub trait DialogBackend<U: DialogUi>: Send + Sync {
+    /// Shows the dialog until the user responds or the operation is cancelled.
+    fn run(
+        &self,
+        ui: U,
+        cancellation: Cancellation,
+    ) -> BoxFuture<'static, Result<U::Output, DialogError>>;
+}
+
+impl<U: DialogUi> DialogBackend<U> for UiProxy {
+    fn run(
+        &self,
+        ui: U,
+        cancellation: Cancellation,
+    ) -> BoxFuture<'static, Result<U::Output, DialogError>> {
+        let proxy = self.clone();
+        async move { run_dialog(&proxy, ui, cancellation).await }.boxed()
+    }
+}
+
+type Reply<T> = Rc<RefCell<Option<Sender<Result<T, DialogError>>>>>;
+
+/// A dialog that is being shown.
+pub struct Dialog<T> {
+    pub window: Window,
+    pub accept: Button,
+    pub context: MainContext,
+    reply: Reply<T>,
+}
+
+impl<T> Clone for Dialog<T> {
+    fn clone(&self) -> Self {
+        Self {
+            window: self.window.clone(),
+            accept: self.accept.clone(),
+            context: self.context.clone(),
+            reply: self.reply.clone(),
+        }
+    }
+}
+
+impl<T> Dialog<T> {
+    /// Completes the dialog with `value` and closes it.
+    pub fn accept_with(&self, value: T) {
+        finish(&self.reply, Ok(value));
+        self.window.close();
+    }
+}
+
+fn finish<T>(reply: &Reply<T>, res: Result<T, DialogError>) {
+    if let Some(send) = reply.borrow_mut().take() {
+        let _ = send.try_send(res);
+    }
+}
+
+/// Shows the dialog until the user responds or the operation is cancelled.
+///
+/// Closing the dialog, e.g. with the cancel button or Escape, rejects it. If the operation
+/// is cancelled, this function returns immediately and the dialog is closed as soon as
+/// the main loop gets to it.
+pub async fn run_dialog<U: DialogUi>(
+    proxy: &UiProxy,
+    ui: U,
+    cancellation: Cancellation,
+) -> Result<U::Output, DialogError> {
+    let (send, recv) = async_channel::bounded(1);
+    let context = proxy.context.clone();
+    let c = cancellation.clone();
+    proxy
+        .context
+        .invoke(move || show_dialog(ui, send, context, c));
+    select! {
+        res = recv.recv().fuse() => res.map_err(|_| DialogError::Closed)?,
+        _ = cancellation.cancelled().fuse() => Err(DialogError::Cancelled),
+    }
+}
+
+fn show_dialog<U: DialogUi>(
+    ui: U,
+    send: Sender<Result<U::Output, DialogError>>,
+    context: MainContext,
+    cancellation: Cancellation,
+) {
+    if cancellation.is_cancelled() {
+        let _ = send.try_send(Err(DialogError::Cancelled));
+        return;
+    }
+    let options = ui.options();
+    let (window, cancel, accept) = dialog_window(&options);
+    let reply: Reply<U::Output> = Rc::new(RefCell::new(Some(send)));
+    let dialog = Dialog {
+        window: window.clone(),
+        accept,
+        context: context.clone(),
+        reply: reply.clone(),
+    };
+    window.set_child(Some(&ui.build(&dialog)));
+    set_parent_window(&window, &options.parent_window);
+    cancel.connect_clicked({
+        let window = window.clone();
+        move |_| window.close()
+    });
+    window.connect_close_request({
+        let reply = reply.clone();
+        move |_| {
+            finish(&reply, Err(DialogError::Rejected));
+            Propagation::Proceed
+        }
+    });
+    window.present();
+    context.spawn_local(async move {
+        cancellation.cancelled().await;
+        finish(&reply, Err(DialogError::Cancelled));
+        window.close();
+    });
+}
+
+/// Creates the window of a dialog with its cancel and accept buttons.
+///
+/// The accept button is the default widget and Escape closes the window.
+fn dialog_window(options: &DialogOptions) -> (Window, Button, Button) {
+    let window = Window::new();
+    window.set_title(Some(&options.title));
+    window.set_modal(options.modal);
+    window.set_default_size(options.default_size.0, options.default_size.1);
+    let cancel = Button::with_mnemonic(&options.cancel_label);
+    let accept = Button::with_mnemonic(&options.accept_label);
+    accept.add_css_class("suggested-action");
+    let header = HeaderBar::new();
+    header.set_show_title_buttons(false);
+    header.pack_start(&cancel);
+    header.pack_end(&accept);
+    window.set_titlebar(Some(&header));
+    window.set_default_widget(Some(&accept));
+    let shortcuts = ShortcutController::new();
+    shortcuts.add_shortcut(Shortcut::new(
+        ShortcutTrigger::parse_string("Escape"),
+        Some(NamedAction::new("window.close")),
+    ));
+    window.add_controller(shortcuts);
+    (window, cancel, accept)
+}
+
+/// Returns a vertical box with the spacing and margins of dialog content.
+pub fn content_box() -> gtk4::Box {
+    let content = gtk4::Box::new(Orientation::Vertical, 12);
+    content.set_margin_top(18);
+    content.set_margin_bottom(18);
+    content.set_margin_start(18);
+    content.set_margin_end(18);
+    content
+}
+
+/// Returns a wrapping, centered label.
+pub fn centered_label(text: &str) -> Label {
+    let label = Label::new(Some(text));
+    label.set_wrap(true);
+    label.set_justify(Justification::Center);
+    label
+}
+
+/// Returns the display name of an application, or its app id if it has no desktop file.
+pub fn app_name(app_id: &str) -> String {
+    DesktopAppInfo::new(&format!("{app_id}.desktop"))
+        .map(|info| info.display_name().to_string())
+        .unwrap_or_else(|| app_id.to_string())
+}
@@ -0,0 +1,115 @@
+use {
+    crate::gui::{
+        centered_label,
+        choices::ChoiceWidgets,
+        content_box,
+        file_chooser::{Choice, FinalChoice},
+        Dialog, DialogOptions, DialogUi,
+    },
+    gtk4::{
+        gio::{prelude::AppInfoExt, DesktopAppInfo},
+        prelude::{BoxExt, ButtonExt, Cast, WidgetExt},
+        Align, Image, Orientation, Widget,
+    },
+    rust_i18n::t,
+};
+
+/// Asks the user to grant or deny access to a resource.
+#[derive(Debug)]
+pub struct AccessUi {
+    pub app_id: String,
+    pub title: String,
+    pub subtitle: String,
+    pub body: String,
+    pub deny_label: Option<String>,
+    pub grant_label: Option<String>,
+    pub icon: Option<String>,
+    pub choices: Vec<Choice>,
+    pub modal: bool,
+    pub parent_window: String,
+}
+
+/// The result of a dialog in which access was granted.
+#[derive(Debug)]
+pub struct AccessResult {
+    pub final_choices: Vec<FinalChoice>,
+}
+
+impl DialogUi for AccessUi {
+    type Output = AccessResult;
+
+    fn options(&self) -> DialogOptions {
+        DialogOptions {
+            title: self.title.clone(),
+            cancel_label: match &self.deny_label {
+                Some(label) => label.clone(),
+                None => t!("_Deny").into_owned(),
+            },
+            accept_label: match &self.grant_label {
+                Some(label) => label.clone(),
+                None => t!("_Allow").into_owned(),
+            },
+            modal: self.modal,
+            default_size: (400, -1),
+            parent_window: self.parent_window.clone(),
+        }
+    }
+
+    fn build(self, dialog: &Dialog<AccessResult>) -> Widget {
+        let content = content_box();
+        let icon = match &self.icon {
+            Some(icon) => Image::from_icon_name(icon),
+            None => self.app_icon(),
+        };
+        icon.set_pixel_size(48);
+        icon.set_halign(Align::Center);
+        content.append(&icon);
+        for (text, class) in [
+            (&self.title, "title-3"),
+            (&self.subtitle, ""),
+            (&self.body, "dim-label"),
+        ] {
+            if text.is_empty() {
+                continue;
+            }
+            let label = centered_label(text);
+            if !class.is_empty() {
+                label.add_css_class(class);
+            }
+            content.append(&label);
+        }
+        let choice_box = gtk4::Box::new(Orientation::Vertical, 6);
+        let choices = ChoiceWidgets::new(&choice_box, &self.choices);
+        if !choices.is_empty() {
+            content.append(&choice_box);
+        }
+        let ids: Vec<_> = self.choices.iter().map(|c| c.id.clone()).collect();
+        dialog.accept.connect_clicked({
+            let dialog = dialog.clone();
+            move |_| {
+                let final_choices = ids
+                    .iter()
+                    .flat_map(|id| {
+                        choices.get(id).map(|variant_id| FinalChoice {
+                            id: id.clone(),
+                            variant_id,
+                        })
+                    })
+                    .collect();
+                dialog.accept_with(AccessResult { final_choices });
+            }
+        });
+        content.upcast()
+    }
+}
+
+impl AccessUi {
+    /// Returns the icon of the requesting application.
+    fn app_icon(&self) -> Image {
+        let icon = DesktopAppInfo::new(&format!("{}.desktop", self.app_id)).and_then(|i| i.icon());
+        match icon {
+            Some(icon) => Image::from_gicon(&icon),
+            None => Image::from_icon_name("dialog-question"),
+        }
+    }
+}
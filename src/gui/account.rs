@@ -0,0 +1,84 @@
+use {
+    crate::gui::{app_name, centered_label, content_box, Dialog, DialogOptions, DialogUi},
+    gtk4::{
+        prelude::{BoxExt, ButtonExt, Cast, WidgetExt},
+        Align, Image, Label, Widget,
+    },
+    rust_i18n::t,
+    std::path::PathBuf,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserInfo {
+    /// The login name.
+    pub id: String,
+    pub name: String,
+    pub image: Option<PathBuf>,
+}
+
+/// Asks the user whether to share their account information.
+///
+/// Completes with `()` if the user agreed to share the information.
+#[derive(Debug)]
+pub struct AccountUi {
+    pub app_id: String,
+    pub reason: Option<String>,
+    pub user: UserInfo,
+    pub modal: bool,
+    pub parent_window: String,
+}
+
+impl DialogUi for AccountUi {
+    type Output = ();
+
+    fn options(&self) -> DialogOptions {
+        DialogOptions {
+            title: t!("Share Details").into_owned(),
+            cancel_label: t!("_Cancel").into_owned(),
+            accept_label: t!("_Share").into_owned(),
+            modal: self.modal,
+            default_size: (360, -1),
+            parent_window: self.parent_window.clone(),
+        }
+    }
+
+    fn build(self, dialog: &Dialog<()>) -> Widget {
+        let content = content_box();
+        content.append(&centered_label(&self.heading()));
+        if let Some(reason) = &self.reason {
+            let reason = centered_label(reason);
+            reason.add_css_class("dim-label");
+            content.append(&reason);
+        }
+        let image = match &self.user.image {
+            Some(path) => Image::from_file(path),
+            None => Image::from_icon_name("avatar-default-symbolic"),
+        };
+        image.set_pixel_size(96);
+        image.set_halign(Align::Center);
+        content.append(&image);
+        let name = Label::new(Some(&self.user.name));
+        name.add_css_class("title-2");
+        content.append(&name);
+        content.append(&Label::new(Some(&self.user.id)));
+        dialog.accept.connect_clicked({
+            let dialog = dialog.clone();
+            move |_| dialog.accept_with(())
+        });
+        content.upcast()
+    }
+}
+
+impl AccountUi {
+    fn heading(&self) -> String {
+        let app = app_name(&self.app_id);
+        if app.is_empty() {
+            return t!("An application wants to access your personal information.").into_owned();
+        }
+        t!(
+            "“%{app}” wants to access your personal information.",
+            app = app
+        )
+        .into_owned()
+    }
+}
@@ -0,0 +1,178 @@
+use {
+    crate::gui::{Dialog, DialogOptions, DialogUi},
+    async_channel::Receiver,
+    gtk4::{
+        gio::{self, AppInfo, DesktopAppInfo},
+        prelude::{AppInfoExt, BoxExt, ButtonExt, Cast, ListBoxRowExt, WidgetExt},
+        Image, Label, ListBox, ListBoxRow, Orientation, PolicyType, ScrolledWindow, SelectionMode,
+        Widget,
+    },
+    rust_i18n::t,
+    std::{cell::RefCell, rc::Rc},
+};
+
+/// Lets the user pick an application.
+///
+/// Every list of choices received from `updates` replaces the choices currently
+/// displayed.
+#[derive(Debug)]
+pub struct AppChooserUi {
+    pub choices: Vec<String>,
+    pub last_choice: Option<String>,
+    pub modal: bool,
+    pub content_type: Option<String>,
+    pub uri: Option<String>,
+    pub filename: Option<String>,
+    pub parent_window: String,
+    pub updates: Receiver<Vec<String>>,
+}
+
+#[derive(Debug)]
+pub struct AppChooserResult {
+    pub choice: String,
+}
+
+impl DialogUi for AppChooserUi {
+    type Output = AppChooserResult;
+
+    fn options(&self) -> DialogOptions {
+        DialogOptions {
+            title: t!("Open With").into_owned(),
+            cancel_label: t!("_Cancel").into_owned(),
+            accept_label: t!("_Open").into_owned(),
+            modal: self.modal,
+            default_size: (400, 500),
+            parent_window: self.parent_window.clone(),
+        }
+    }
+
+    fn build(self, dialog: &Dialog<AppChooserResult>) -> Widget {
+        let heading = Label::new(Some(&self.heading()));
+        heading.set_wrap(true);
+        heading.set_margin_top(12);
+        heading.set_margin_bottom(12);
+        heading.set_margin_start(12);
+        heading.set_margin_end(12);
+        let list = ListBox::new();
+        list.set_selection_mode(SelectionMode::Browse);
+        list.set_activate_on_single_click(false);
+        let scrolled = ScrolledWindow::new();
+        scrolled.set_policy(PolicyType::Never, PolicyType::Automatic);
+        scrolled.set_vexpand(true);
+        scrolled.set_child(Some(&list));
+        let content = gtk4::Box::new(Orientation::Vertical, 0);
+        content.append(&heading);
+        content.append(&scrolled);
+        let accept = &dialog.accept;
+        let ids = Rc::new(RefCell::new(vec![]));
+        *ids.borrow_mut() = self.fill_list(&list, &self.choices, self.last_choice.as_deref());
+        accept.set_sensitive(list.selected_row().is_some());
+        list.connect_row_selected({
+            let accept = accept.clone();
+            move |_, row| accept.set_sensitive(row.is_some())
+        });
+        list.connect_row_activated({
+            let accept = accept.clone();
+            move |_, _| accept.emit_clicked()
+        });
+        accept.connect_clicked({
+            let dialog = dialog.clone();
+            let ids = ids.clone();
+            let list = list.clone();
+            move |_| {
+                let Some(row) = list.selected_row() else {
+                    return;
+                };
+                let Some(choice) = ids.borrow().get(row.index() as usize).cloned() else {
+                    return;
+                };
+                dialog.accept_with(AppChooserResult { choice });
+            }
+        });
+        dialog.context.spawn_local(async move {
+            while let Ok(choices) = self.updates.recv().await {
+                let selected = list
+                    .selected_row()
+                    .and_then(|row| ids.borrow().get(row.index() as usize).cloned());
+                let selected = selected.as_deref().or(self.last_choice.as_deref());
+                *ids.borrow_mut() = self.fill_list(&list, &choices, selected);
+            }
+        });
+        content.upcast()
+    }
+}
+
+impl AppChooserUi {
+    fn heading(&self) -> String {
+        if let Some(name) = &self.filename {
+            return t!("Choose an application to open “%{name}”.", name = name).into_owned();
+        }
+        if let Some(uri) = &self.uri {
+            return t!("Choose an application to open “%{name}”.", name = uri).into_owned();
+        }
+        if let Some(content_type) = &self.content_type {
+            let description = gio::content_type_get_description(content_type);
+            return t!(
+                "Choose an application to open files of type “%{content_type}”.",
+                content_type = description
+            )
+            .into_owned();
+        }
+        t!("Choose an application.").into_owned()
+    }
+
+    /// Replaces the rows of the list and returns the application ids of the new rows.
+    fn fill_list(&self, list: &ListBox, choices: &[String], selected: Option<&str>) -> Vec<String> {
+        while let Some(row) = list.row_at_index(0) {
+            list.remove(&row);
+        }
+        let candidates = self.candidates(choices);
+        for (idx, (id, info)) in candidates.iter().enumerate() {
+            let content = gtk4::Box::new(Orientation::Horizontal, 12);
+            content.set_margin_top(6);
+            content.set_margin_bottom(6);
+            content.set_margin_start(12);
+            content.set_margin_end(12);
+            let icon = match info.icon() {
+                Some(icon) => Image::from_gicon(&icon),
+                None => Image::from_icon_name("application-x-executable"),
+            };
+            icon.set_pixel_size(32);
+            content.append(&icon);
+            content.append(&Label::new(Some(&info.display_name())));
+            let row = ListBoxRow::new();
+            row.set_child(Some(&content));
+            list.append(&row);
+            if idx == 0 || selected == Some(id.as_str()) {
+                list.select_row(Some(&row));
+            }
+        }
+        candidates.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Resolves the choices of the client and appends all other applications that
+    /// can handle the content type.
+    fn candidates(&self, choices: &[String]) -> Vec<(String, AppInfo)> {
+        let mut res: Vec<(String, AppInfo)> = vec![];
+        for id in choices {
+            if let Some(info) = DesktopAppInfo::new(&format!("{id}.desktop")) {
+                res.push((id.clone(), info.upcast()));
+            }
+        }
+        if let Some(content_type) = &self.content_type {
+            for info in AppInfo::all_for_type(content_type) {
+                if !info.should_show() {
+                    continue;
+                }
+                let Some(id) = info.id() else {
+                    continue;
+                };
+                let id = id.strip_suffix(".desktop").unwrap_or(&id).to_string();
+                if res.iter().all(|(i, _)| *i != id) {
+                    res.push((id, info));
+                }
+            }
+        }
+        res
+    }
+}
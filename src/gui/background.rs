@@ -0,0 +1,74 @@
+use {
+    crate::gui::{centered_label, content_box, Dialog, DialogOptions, DialogUi},
+    gtk4::{
+        gio::{prelude::AppInfoExt, DesktopAppInfo},
+        prelude::{BoxExt, ButtonExt, Cast, WidgetExt},
+        Align, Image, Widget,
+    },
+    rust_i18n::t,
+};
+
+/// Asks the user whether an application may keep running in the background.
+///
+/// Completes with `true` if the application may keep running. Closing the dialog rejects
+/// it, which also means that the application may not keep running.
+#[derive(Debug)]
+pub struct BackgroundUi {
+    pub app_id: String,
+    /// The name of the application as reported by xdg-desktop-portal.
+    pub name: String,
+}
+
+impl DialogUi for BackgroundUi {
+    type Output = bool;
+
+    fn options(&self) -> DialogOptions {
+        DialogOptions {
+            title: t!("Background Activity").into_owned(),
+            cancel_label: t!("_Don’t Allow").into_owned(),
+            accept_label: t!("_Allow").into_owned(),
+            modal: false,
+            default_size: (400, -1),
+            parent_window: String::new(),
+        }
+    }
+
+    fn build(self, dialog: &Dialog<bool>) -> Widget {
+        let content = content_box();
+        let info = DesktopAppInfo::new(&format!("{}.desktop", self.app_id));
+        let icon = match info.as_ref().and_then(|i| i.icon()) {
+            Some(icon) => Image::from_gicon(&icon),
+            None => Image::from_icon_name("application-x-executable"),
+        };
+        icon.set_pixel_size(48);
+        icon.set_halign(Align::Center);
+        content.append(&icon);
+        let heading = centered_label(&self.heading(info));
+        heading.add_css_class("title-3");
+        content.append(&heading);
+        let body = centered_label(&t!(
+            "Applications running in the background use resources even when you are not using them."
+        ));
+        body.add_css_class("dim-label");
+        content.append(&body);
+        dialog.accept.connect_clicked({
+            let dialog = dialog.clone();
+            move |_| dialog.accept_with(true)
+        });
+        content.upcast()
+    }
+}
+
+impl BackgroundUi {
+    fn heading(&self, info: Option<DesktopAppInfo>) -> String {
+        let app = match info {
+            _ if !self.name.is_empty() => self.name.clone(),
+            Some(info) => info.display_name().to_string(),
+            None => self.app_id.clone(),
+        };
+        if app.is_empty() {
+            return t!("An application wants to run in the background.").into_owned();
+        }
+        t!("“%{app}” wants to run in the background.", app = app).into_owned()
+    }
+}
@@ -0,0 +1,69 @@
+use {
+    crate::gui::file_chooser::Choice,
+    gtk4::{
+        prelude::{BoxExt, CheckButtonExt, WidgetExt},
+        Align, CheckButton, DropDown, Label, Orientation, INVALID_LIST_POSITION,
+    },
+};
+
+/// Widgets that let the user pick a variant of each choice.
+///
+/// Choices without variants are shown as check buttons and have the values `true` and
+/// `false`. All other choices are shown as drop-downs.
+pub struct ChoiceWidgets {
+    widgets: Vec<(String, ChoiceWidget)>,
+}
+
+enum ChoiceWidget {
+    Toggle(CheckButton),
+    Variants(DropDown, Vec<String>),
+}
+
+impl ChoiceWidgets {
+    /// Appends widgets for the choices to `parent`.
+    pub fn new<'a>(parent: &gtk4::Box, choices: impl IntoIterator<Item = &'a Choice>) -> Self {
+        let widgets = choices
+            .into_iter()
+            .map(|c| (c.id.clone(), add_choice(parent, c)))
+            .collect();
+        Self { widgets }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.widgets.is_empty()
+    }
+
+    /// Returns the selected variant of a choice.
+    pub fn get(&self, id: &str) -> Option<String> {
+        let (_, widget) = self.widgets.iter().find(|(i, _)| i == id)?;
+        match widget {
+            ChoiceWidget::Toggle(button) => Some(button.is_active().to_string()),
+            ChoiceWidget::Variants(drop_down, ids) => match drop_down.selected() {
+                INVALID_LIST_POSITION => None,
+                idx => ids.get(idx as usize).cloned(),
+            },
+        }
+    }
+}
+
+fn add_choice(parent: &gtk4::Box, choice: &Choice) -> ChoiceWidget {
+    if choice.variants.is_empty() {
+        let button = CheckButton::with_label(&choice.label);
+        button.set_active(choice.default == "true");
+        parent.append(&button);
+        return ChoiceWidget::Toggle(button);
+    }
+    let labels: Vec<_> = choice.variants.iter().map(|v| v.label.as_str()).collect();
+    let ids: Vec<_> = choice.variants.iter().map(|v| v.id.clone()).collect();
+    let drop_down = DropDown::from_strings(&labels);
+    if let Some(idx) = ids.iter().position(|id| *id == choice.default) {
+        drop_down.set_selected(idx as u32);
+    }
+    let label = Label::new(Some(&choice.label));
+    let row = gtk4::Box::new(Orientation::Horizontal, 6);
+    row.set_valign(Align::Center);
+    row.append(&label);
+    row.append(&drop_down);
+    parent.append(&row);
+    ChoiceWidget::Variants(drop_down, ids)
+}
@@ -0,0 +1,116 @@
+#[allow(deprecated)] // GtkColorChooser is deprecated since GTK 4.10
+use {
+    crate::gui::{app_name, centered_label, content_box, Dialog, DialogOptions, DialogUi},
+    gtk4::{
+        gdk::RGBA,
+        prelude::{BoxExt, ButtonExt, Cast, ColorChooserExt, EditableExt, EntryExt, WidgetExt},
+        ColorChooserWidget, Entry, Widget,
+    },
+    rust_i18n::t,
+};
+
+/// An sRGB color with components between 0 and 1.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Color {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+}
+
+/// Lets the user pick a color.
+#[derive(Debug)]
+pub struct ColorUi {
+    pub app_id: String,
+    pub modal: bool,
+    pub parent_window: String,
+}
+
+impl DialogUi for ColorUi {
+    type Output = Color;
+
+    fn options(&self) -> DialogOptions {
+        DialogOptions {
+            title: t!("Pick Color").into_owned(),
+            cancel_label: t!("_Cancel").into_owned(),
+            accept_label: t!("_Select").into_owned(),
+            modal: self.modal,
+            default_size: (-1, -1),
+            parent_window: self.parent_window.clone(),
+        }
+    }
+
+    #[allow(deprecated)]
+    fn build(self, dialog: &Dialog<Color>) -> Widget {
+        let content = content_box();
+        content.append(&centered_label(&self.heading()));
+        let chooser = ColorChooserWidget::new();
+        chooser.set_use_alpha(false);
+        content.append(&chooser);
+        // Accepts anything that `gdk_rgba_parse` understands, e.g. `#ff8000` or
+        // `rgb(255,128,0)`.
+        let entry = Entry::new();
+        entry.set_placeholder_text(Some(&t!("Hex or RGB value")));
+        entry.set_text(&hex(chooser.rgba()));
+        content.append(&entry);
+        chooser.connect_rgba_notify({
+            let entry = entry.clone();
+            move |chooser| {
+                let rgba = chooser.rgba();
+                if RGBA::parse(entry.text().trim()).ok() != Some(rgba) {
+                    entry.set_text(&hex(rgba));
+                }
+            }
+        });
+        entry.connect_changed({
+            let chooser = chooser.clone();
+            let accept = dialog.accept.clone();
+            move |entry| {
+                let rgba = RGBA::parse(entry.text().trim());
+                accept.set_sensitive(rgba.is_ok());
+                if let Ok(rgba) = rgba {
+                    chooser.set_rgba(&rgba);
+                }
+            }
+        });
+        entry.set_activates_default(true);
+        let select = {
+            let dialog = dialog.clone();
+            move |rgba: RGBA| {
+                dialog.accept_with(Color {
+                    red: rgba.red() as f64,
+                    green: rgba.green() as f64,
+                    blue: rgba.blue() as f64,
+                });
+            }
+        };
+        chooser.connect_color_activated({
+            let select = select.clone();
+            move |_, rgba| select(*rgba)
+        });
+        dialog.accept.connect_clicked({
+            let chooser = chooser.clone();
+            move |_| select(chooser.rgba())
+        });
+        content.upcast()
+    }
+}
+
+impl ColorUi {
+    fn heading(&self) -> String {
+        let app = app_name(&self.app_id);
+        if app.is_empty() {
+            return t!("An application wants to pick a color.").into_owned();
+        }
+        t!("“%{app}” wants to pick a color.", app = app).into_owned()
+    }
+}
+
+fn hex(rgba: RGBA) -> String {
+    let c = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        c(rgba.red()),
+        c(rgba.green()),
+        c(rgba.blue())
+    )
+}
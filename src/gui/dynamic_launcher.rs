@@ -0,0 +1,106 @@
+use {
+    crate::{
+        gui::{app_name, centered_label, content_box, Dialog, DialogOptions, DialogUi},
+        utils::icon::Icon,
+    },
+    error_reporter::Report,
+    gtk4::{
+        gdk_pixbuf::Pixbuf,
+        gio::{Cancellable, MemoryInputStream, ThemedIcon},
+        glib,
+        prelude::{BoxExt, ButtonExt, Cast, EditableExt, EntryExt, WidgetExt},
+        Align, Entry, Image, Widget,
+    },
+    rust_i18n::t,
+};
+
+/// Asks the user whether to create a launcher.
+///
+/// Completes with the name of the launcher if the user agreed to create it.
+#[derive(Debug)]
+pub struct LauncherUi {
+    pub app_id: String,
+    pub name: String,
+    pub icon: Option<Icon>,
+    /// The URL of a web application.
+    pub target: Option<String>,
+    pub editable_name: bool,
+    pub modal: bool,
+    pub parent_window: String,
+}
+
+impl DialogUi for LauncherUi {
+    type Output = String;
+
+    fn options(&self) -> DialogOptions {
+        DialogOptions {
+            title: t!("Create Launcher").into_owned(),
+            cancel_label: t!("_Cancel").into_owned(),
+            accept_label: t!("C_reate").into_owned(),
+            modal: self.modal,
+            default_size: (360, -1),
+            parent_window: self.parent_window.clone(),
+        }
+    }
+
+    fn build(self, dialog: &Dialog<String>) -> Widget {
+        let content = content_box();
+        content.append(&centered_label(&self.heading()));
+        let image = self.image();
+        image.set_pixel_size(64);
+        image.set_halign(Align::Center);
+        content.append(&image);
+        let name = Entry::new();
+        name.set_text(&self.name);
+        name.set_editable(self.editable_name);
+        name.set_can_focus(self.editable_name);
+        name.set_activates_default(true);
+        name.connect_changed({
+            let accept = dialog.accept.clone();
+            move |name| accept.set_sensitive(!name.text().trim().is_empty())
+        });
+        dialog.accept.set_sensitive(!self.name.trim().is_empty());
+        content.append(&name);
+        if let Some(target) = &self.target {
+            let target = centered_label(target);
+            target.add_css_class("dim-label");
+            content.append(&target);
+        }
+        dialog.accept.connect_clicked({
+            let dialog = dialog.clone();
+            move |_| dialog.accept_with(name.text().trim().to_string())
+        });
+        content.upcast()
+    }
+}
+
+impl LauncherUi {
+    fn heading(&self) -> String {
+        let app = app_name(&self.app_id);
+        if app.is_empty() {
+            return t!("An application wants to create a launcher.").into_owned();
+        }
+        t!("“%{app}” wants to create a launcher.", app = app).into_owned()
+    }
+
+    fn image(&self) -> Image {
+        match &self.icon {
+            Some(Icon::Themed(names)) => {
+                let names: Vec<_> = names.iter().map(|n| n.as_str()).collect();
+                Image::from_gicon(&ThemedIcon::from_names(&names))
+            }
+            Some(Icon::File(path)) => Image::from_file(path),
+            Some(Icon::Bytes(bytes)) => {
+                let stream = MemoryInputStream::from_bytes(&glib::Bytes::from(bytes));
+                match Pixbuf::from_stream(&stream, None::<&Cancellable>) {
+                    Ok(pixbuf) => Image::from_pixbuf(Some(&pixbuf)),
+                    Err(e) => {
+                        log::warn!("Could not load the launcher icon: {}", Report::new(e));
+                        Image::from_icon_name("application-x-executable")
+                    }
+                }
+            }
+            None => Image::from_icon_name("application-x-executable"),
+        }
+    }
+}
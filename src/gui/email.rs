@@ -0,0 +1,85 @@
+use {
+    crate::gui::UiProxy,
+    futures_util::{future::BoxFuture, FutureExt},
+    gtk4::{
+        gdk::Display,
+        gio::{AppInfo, AppLaunchContext},
+        glib,
+        prelude::{AppLaunchContextExt, DisplayExt},
+    },
+    std::{
+        ffi::OsString,
+        io,
+        process::{Command, Stdio},
+        thread,
+    },
+    thiserror::Error,
+};
+
+#[derive(Debug, Error)]
+pub enum EmailError {
+    #[error("Operation could not be started")]
+    Closed,
+    #[error("The mail client command is empty")]
+    EmptyCommand,
+    #[error("Could not spawn the mail client")]
+    Spawn(#[source] io::Error),
+    #[error("Could not launch the default mail client")]
+    Launch(#[source] glib::Error),
+}
+
+/// Starts mail clients.
+pub trait MailClient: Send + Sync {
+    /// Opens a composer window for the draft.
+    ///
+    /// This completes once the mail client has been started.
+    fn compose(&self, draft: Draft) -> BoxFuture<'static, Result<(), EmailError>>;
+}
+
+impl MailClient for UiProxy {
+    fn compose(&self, draft: Draft) -> BoxFuture<'static, Result<(), EmailError>> {
+        let (send, recv) = async_channel::bounded(1);
+        self.context.invoke(move || {
+            let _ = send.try_send(draft.launch());
+        });
+        async move { recv.recv().await.map_err(|_| EmailError::Closed)? }.boxed()
+    }
+}
+
+#[derive(Debug)]
+pub struct Draft {
+    /// The `mailto:` URI of the draft, including attachments.
+    pub uri: String,
+    /// The command to start instead of the default `mailto` handler.
+    ///
+    /// The URI is appended to the arguments.
+    pub command: Option<Vec<OsString>>,
+    pub activation_token: Option<String>,
+}
+
+impl Draft {
+    fn launch(self) -> Result<(), EmailError> {
+        let Some(command) = self.command else {
+            let context = match Display::default() {
+                Some(display) => display.app_launch_context().into(),
+                None => AppLaunchContext::new(),
+            };
+            if let Some(token) = &self.activation_token {
+                context.setenv("XDG_ACTIVATION_TOKEN", token);
+                context.setenv("DESKTOP_STARTUP_ID", token);
+            }
+            return AppInfo::launch_default_for_uri(&self.uri, Some(&context))
+                .map_err(EmailError::Launch);
+        };
+        let (program, args) = command.split_first().ok_or(EmailError::EmptyCommand)?;
+        let mut cmd = Command::new(program);
+        cmd.args(args).arg(&self.uri).stdin(Stdio::null());
+        if let Some(token) = &self.activation_token {
+            cmd.env("XDG_ACTIVATION_TOKEN", token);
+            cmd.env("DESKTOP_STARTUP_ID", token);
+        }
+        let mut child = cmd.spawn().map_err(EmailError::Spawn)?;
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
@@ -1,46 +1,82 @@
+#[allow(deprecated)] // GtkFileChooser is deprecated since GTK 4.10
 use {
-    crate::{gui::UiProxy, utils::file_chooser_ext::FileChooserExtManualFixed},
-    async_channel::{Receiver, Sender},
-    gdk4_wayland::WaylandToplevel,
+    crate::{
+        config::{app_rules::AppPolicy, Config},
+        gui::UiProxy,
+        utils::cancellation::Cancellation,
+    },
+    async_channel::Sender,
+    futures_util::{future::BoxFuture, select, FutureExt},
     gtk4::{
         gio::File,
         glib::MainContext,
-        prelude::{
-            Cast, DialogExt, FileChooserExt, FileChooserExtManual, FileExt, GtkWindowExt,
-            NativeExt, RecentManagerExt, WidgetExt,
-        },
-        FileChooserAction, FileChooserDialog, FileFilter, RecentData, RecentManager, ResponseType,
-        Widget, Window,
+        prelude::{Cast, FileChooserExt, FileChooserExtManual, FileExt, IsA, RecentManagerExt},
+        FileChooser, FileChooserAction, FileFilter, RecentData, RecentManager,
     },
     rust_i18n::t,
     std::{
         cell::Cell,
         collections::{HashMap, HashSet},
         rc::Rc,
+        sync::Arc,
     },
     thiserror::Error,
 };
 
+#[cfg(not(feature = "file-chooser-window"))]
+mod dialog;
+#[cfg(feature = "file-chooser-window")]
+mod window;
+
+#[cfg(not(feature = "file-chooser-window"))]
+use dialog::Dialog;
+#[cfg(feature = "file-chooser-window")]
+use window::Dialog;
+
 #[derive(Debug, Error)]
 pub enum FileChooserError {
     #[error("Operation could not be started")]
     Closed,
     #[error("Operation was rejected")]
     Rejected,
+    #[error("Operation was cancelled")]
+    Cancelled,
+}
+
+/// A frontend that shows file choosers.
+pub trait FileChooserBackend: Send + Sync {
+    /// Shows a file chooser until the user responds or the operation is cancelled.
+    fn run(
+        &self,
+        ui: FileChooserUi,
+        cancellation: Cancellation,
+    ) -> BoxFuture<'static, Result<FileChooserResult, FileChooserError>>;
+}
+
+impl FileChooserBackend for UiProxy {
+    fn run(
+        &self,
+        ui: FileChooserUi,
+        cancellation: Cancellation,
+    ) -> BoxFuture<'static, Result<FileChooserResult, FileChooserError>> {
+        let proxy = self.clone();
+        async move { ui.run(&proxy, cancellation).await }.boxed()
+    }
 }
 
-#[derive(Eq, PartialEq, Clone)]
+#[derive(Eq, PartialEq, Clone, Debug)]
 pub struct Filter {
     pub name: String,
     pub elements: Vec<FilterKind>,
 }
 
-#[derive(Eq, PartialEq, Clone)]
+#[derive(Eq, PartialEq, Clone, Debug)]
 pub enum FilterKind {
     Glob(String),
     Mime(String),
 }
 
+#[derive(Eq, PartialEq, Debug)]
 pub struct Choice {
     pub id: String,
     pub label: String,
@@ -48,16 +84,19 @@ pub struct Choice {
     pub variants: Vec<ChoiceVariant>,
 }
 
+#[derive(Eq, PartialEq, Debug)]
 pub struct ChoiceVariant {
     pub id: String,
     pub label: String,
 }
 
+#[derive(Eq, PartialEq, Debug)]
 pub struct FinalChoice {
     pub id: String,
     pub variant_id: String,
 }
 
+#[derive(Debug)]
 pub struct FileChooserUi {
     pub title: String,
     pub multiple: bool,
@@ -68,13 +107,17 @@ pub struct FileChooserUi {
     pub current_filter: Option<Filter>,
     pub current_name: Option<String>,
     pub current_folder: Option<String>,
+    /// The path of the file to select, not a URI.
     pub current_filename: Option<String>,
     pub choices: Option<Vec<Choice>>,
     pub save: bool,
     pub parent_window: String,
     pub app_id: String,
+    pub config: Arc<Config>,
+    pub policy: AppPolicy,
 }
 
+#[derive(Debug)]
 pub struct FileChooserResult {
     pub uris: Vec<String>,
     pub current_filter: Option<Filter>,
@@ -82,168 +125,196 @@ pub struct FileChooserResult {
     pub writeable: bool,
 }
 
-struct DialogData {
-    dialog: FileChooserDialog,
-    read_only_choice: String,
+/// The filters of a file chooser.
+struct Filters {
     filters: HashMap<FileFilter, Filter>,
+    current: Cell<Option<FileFilter>>,
+}
+
+impl Filters {
+    /// Returns the filter that was selected last.
+    fn current(&self) -> Option<Filter> {
+        self.current
+            .take()
+            .and_then(|f| self.filters.get(&f).cloned())
+    }
 }
 
 impl FileChooserUi {
-    pub async fn run(self, proxy: &UiProxy) -> Result<FileChooserResult, FileChooserError> {
+    /// Shows the dialog until the user responds or the operation is cancelled.
+    ///
+    /// If the operation is cancelled, this function returns immediately and the dialog is
+    /// closed as soon as the main loop gets to it.
+    pub async fn run(
+        self,
+        proxy: &UiProxy,
+        cancellation: Cancellation,
+    ) -> Result<FileChooserResult, FileChooserError> {
         let (send, recv) = async_channel::bounded(1);
-        let (_send, close_on_close) = async_channel::bounded(1);
         let context = proxy.context.clone();
+        let c = cancellation.clone();
         proxy
             .context
-            .invoke(move || self.run_impl(send, context, close_on_close));
-        recv.recv().await.map_err(|_| FileChooserError::Closed)?
+            .invoke(move || self.run_impl(send, context, c));
+        select! {
+            res = recv.recv().fuse() => res.map_err(|_| FileChooserError::Closed)?,
+            _ = cancellation.cancelled().fuse() => Err(FileChooserError::Cancelled),
+        }
     }
 
     fn run_impl(
         self,
         send: Sender<Result<FileChooserResult, FileChooserError>>,
         context: MainContext,
-        close_on_close: Receiver<()>,
+        cancellation: Cancellation,
     ) {
-        let DialogData {
-            dialog,
-            read_only_choice,
-            filters,
-        } = self.build_dialog();
-        let current_filter = Rc::new(Cell::new(dialog.filter()));
-        let cf = current_filter.clone();
-        dialog.connect_filter_notify(move |f| cf.set(f.filter()));
-        let cf = current_filter.clone();
-        dialog.connect_response(move |dialog, r| {
-            let res = match r {
-                ResponseType::Ok => {
-                    let files: Vec<_> = dialog
-                        .files()
-                        .into_iter()
-                        .map(|f| f.unwrap().downcast::<File>().unwrap().uri().into())
-                        .collect();
-                    add_recent(&self.app_id, &files);
-                    let filter = cf.take().and_then(|f| filters.get(&f).cloned());
-                    let choices: Vec<_> = self
-                        .choices
-                        .as_deref()
-                        .unwrap_or_default()
-                        .iter()
-                        .flat_map(|c| {
-                            dialog.choice(&c.id).map(|v| FinalChoice {
-                                id: c.id.to_string(),
-                                variant_id: v.to_string(),
-                            })
-                        })
-                        .collect();
-                    let writeable = dialog
-                        .choice(&read_only_choice)
-                        .map(|v| v == "false")
-                        .unwrap_or(false);
-                    Ok(FileChooserResult {
-                        uris: files,
-                        current_filter: filter,
-                        final_choices: self.choices.is_some().then_some(choices),
-                        writeable,
-                    })
-                }
-                _ => Err(FileChooserError::Rejected),
+        if cancellation.is_cancelled() {
+            let _ = send.try_send(Err(FileChooserError::Cancelled));
+            return;
+        }
+        let read_only = self.read_only_choice();
+        let dialog = Dialog::new(&self, read_only.as_ref());
+        let on_cancel = send.clone();
+        dialog.connect_response(move |dialog, accepted| {
+            let res = match accepted {
+                true => Ok(self.result(dialog, read_only.as_ref())),
+                false => Err(FileChooserError::Rejected),
             };
-            let _ = send.send_blocking(res);
+            let _ = send.try_send(res);
             dialog.close();
         });
-        dialog.show();
+        dialog.present();
         context.spawn_local(async move {
-            let _ = close_on_close.recv().await;
+            cancellation.cancelled().await;
+            let _ = on_cancel.try_send(Err(FileChooserError::Cancelled));
             dialog.close();
         });
     }
 
-    fn build_dialog(&self) -> DialogData {
-        let action = match (self.directory, self.save) {
+    fn result(&self, dialog: &Dialog, read_only: Option<&Choice>) -> FileChooserResult {
+        let files = dialog.files();
+        let record_recent = self
+            .policy
+            .record_recent
+            .unwrap_or(self.config.file_chooser.record_recent);
+        if record_recent {
+            add_recent(&self.app_id, &files);
+        }
+        let choices: Vec<_> = self
+            .choices
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|c| {
+                dialog.choice(&c.id).map(|v| FinalChoice {
+                    id: c.id.to_string(),
+                    variant_id: v,
+                })
+            })
+            .collect();
+        let writeable = match self.policy.read_only {
+            Some(read_only) => !read_only,
+            None => read_only
+                .and_then(|c| dialog.choice(&c.id))
+                .map(|v| v == "false")
+                .unwrap_or(false),
+        };
+        FileChooserResult {
+            uris: files,
+            current_filter: dialog.current_filter(),
+            final_choices: self.choices.is_some().then_some(choices),
+            writeable,
+        }
+    }
+
+    fn action(&self) -> FileChooserAction {
+        match (self.directory, self.save) {
             (true, _) => FileChooserAction::SelectFolder,
             (_, true) => FileChooserAction::Save,
             (false, _) => FileChooserAction::Open,
-        };
-        let accept_label = match self.save {
-            true => t!("_Save"),
-            false => t!("_Open"),
-        };
-        let buttons = [
-            (
-                self.accept_label.as_deref().unwrap_or(&accept_label),
-                ResponseType::Ok,
-            ),
-            (&t!("_Cancel"), ResponseType::Cancel),
-        ];
-        let dialog =
-            FileChooserDialog::new(Some(self.title.clone()), Window::NONE, action, &buttons);
-        dialog.set_select_multiple(self.multiple);
-        dialog.set_modal(self.modal);
-        dialog.set_default_response(ResponseType::Ok);
-        let mut filters_map = HashMap::new();
+        }
+    }
+
+    fn accept_label(&self) -> String {
+        if let Some(label) = &self.accept_label {
+            return label.clone();
+        }
+        match self.save {
+            true => t!("_Save").into_owned(),
+            false => t!("_Open").into_owned(),
+        }
+    }
+
+    /// Returns the "Open files read-only" choice if the user gets to choose.
+    ///
+    /// The id of the choice does not conflict with the choices of the client.
+    fn read_only_choice(&self) -> Option<Choice> {
+        if self.action() != FileChooserAction::Open || self.policy.read_only.is_some() {
+            return None;
+        }
+        let choice_ids: HashSet<_> = self
+            .choices
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect();
+        let mut id = "_read_only".to_string();
+        while choice_ids.contains(id.as_str()) {
+            id.push('_');
+        }
+        Some(Choice {
+            id,
+            label: t!("Open files read-only").into_owned(),
+            default: self.config.file_chooser.read_only.to_string(),
+            variants: vec![],
+        })
+    }
+
+    /// Applies the options that do not depend on the frontend.
+    #[allow(deprecated)]
+    fn configure(&self, chooser: &impl IsA<FileChooser>) -> Rc<Filters> {
+        chooser.set_select_multiple(self.multiple);
+        let mut filters = HashMap::new();
         if let Some(f) = &self.filters {
             for filter in f {
                 let is_current = self.current_filter.as_ref() == Some(filter);
                 let f = map_filter(filter);
-                dialog.add_filter(&f);
+                chooser.add_filter(&f);
                 if is_current {
-                    dialog.set_filter(&f);
+                    chooser.set_filter(&f);
                 }
-                filters_map.insert(f, filter.clone());
+                filters.insert(f, filter.clone());
             }
         }
         if let Some(f) = &self.current_name {
-            dialog.set_current_name(f);
+            chooser.set_current_name(f);
         }
         if let Some(f) = &self.current_folder {
-            let _ = dialog.set_current_folder(Some(&File::for_path(f)));
+            let _ = chooser.set_current_folder(Some(&File::for_path(f)));
         }
         if let Some(f) = &self.current_filename {
-            let _ = dialog.set_file(&File::for_uri(f));
-        }
-        let mut read_only_id = String::new();
-        if action == FileChooserAction::Open {
-            let choice_ids: HashSet<_> = self
-                .choices
-                .as_deref()
-                .unwrap_or_default()
-                .iter()
-                .map(|c| c.id.as_str())
-                .collect();
-            read_only_id = "_read_only".to_string();
-            while choice_ids.contains(read_only_id.as_str()) {
-                read_only_id.push('_');
-            }
-            dialog.add_choice_fixed(&read_only_id, t!("Open files read-only").as_ref(), &[]);
-            dialog.set_choice(&read_only_id, "true");
-        }
-        if let Some(choices) = &self.choices {
-            for choice in choices {
-                let mut variants = vec![];
-                for variant in &choice.variants {
-                    variants.push((variant.id.as_str(), variant.label.as_str()));
-                }
-                dialog.add_choice_fixed(&choice.id, &choice.label, &variants);
-                dialog.set_choice(&choice.id, &choice.default);
-            }
-        }
-        dialog.upcast_ref::<Widget>().realize();
-        if let Some(parent) = self.parent_window.strip_prefix("wayland:") {
-            if let Some(surface) = dialog.surface() {
-                if let Some(toplevel) = surface.downcast_ref::<WaylandToplevel>() {
-                    toplevel.set_transient_for_exported(parent);
-                }
-            }
-        }
-        DialogData {
-            dialog,
-            read_only_choice: read_only_id,
-            filters: filters_map,
+            let _ = chooser.set_file(&File::for_path(f));
         }
+        let filters = Rc::new(Filters {
+            filters,
+            current: Cell::new(chooser.filter()),
+        });
+        let f = filters.clone();
+        chooser.connect_filter_notify(move |c| f.current.set(c.filter()));
+        filters
     }
 }
 
+#[allow(deprecated)]
+fn selected_uris(chooser: &impl IsA<FileChooser>) -> Vec<String> {
+    chooser
+        .files()
+        .into_iter()
+        .map(|f| f.unwrap().downcast::<File>().unwrap().uri().into())
+        .collect()
+}
+
 fn map_filter(f: &Filter) -> FileFilter {
     let gf = FileFilter::new();
     gf.set_name(Some(&f.name));
@@ -0,0 +1,75 @@
+use {
+    crate::{
+        gui::{
+            file_chooser::{selected_uris, Choice, FileChooserUi, Filter, Filters},
+            set_parent_window,
+        },
+        utils::file_chooser_ext::FileChooserExtManualFixed,
+    },
+    gtk4::{
+        prelude::{DialogExt, FileChooserExt, GtkWindowExt},
+        FileChooserDialog, ResponseType, Window,
+    },
+    rust_i18n::t,
+    std::rc::Rc,
+};
+
+/// A file chooser built on [`FileChooserDialog`].
+#[derive(Clone)]
+pub struct Dialog {
+    dialog: FileChooserDialog,
+    filters: Rc<Filters>,
+}
+
+impl Dialog {
+    pub fn new(ui: &FileChooserUi, read_only: Option<&Choice>) -> Self {
+        let accept_label = ui.accept_label();
+        let buttons = [
+            (accept_label.as_str(), ResponseType::Ok),
+            (&t!("_Cancel"), ResponseType::Cancel),
+        ];
+        let dialog =
+            FileChooserDialog::new(Some(ui.title.clone()), Window::NONE, ui.action(), &buttons);
+        dialog.set_modal(ui.modal);
+        dialog.set_default_response(ResponseType::Ok);
+        let filters = ui.configure(&dialog);
+        let choices = ui.choices.as_deref().unwrap_or_default();
+        for choice in read_only.into_iter().chain(choices) {
+            let mut variants = vec![];
+            for variant in &choice.variants {
+                variants.push((variant.id.as_str(), variant.label.as_str()));
+            }
+            dialog.add_choice_fixed(&choice.id, &choice.label, &variants);
+            dialog.set_choice(&choice.id, &choice.default);
+        }
+        set_parent_window(&dialog, &ui.parent_window);
+        Self { dialog, filters }
+    }
+
+    /// Calls `f` with `true` if the user accepted the dialog and `false` otherwise.
+    pub fn connect_response(&self, f: impl Fn(&Self, bool) + 'static) {
+        let slf = self.clone();
+        self.dialog
+            .connect_response(move |_, r| f(&slf, r == ResponseType::Ok));
+    }
+
+    pub fn files(&self) -> Vec<String> {
+        selected_uris(&self.dialog)
+    }
+
+    pub fn current_filter(&self) -> Option<Filter> {
+        self.filters.current()
+    }
+
+    pub fn choice(&self, id: &str) -> Option<String> {
+        self.dialog.choice(id).map(|v| v.to_string())
+    }
+
+    pub fn present(&self) {
+        self.dialog.present();
+    }
+
+    pub fn close(&self) {
+        self.dialog.close();
+    }
+}
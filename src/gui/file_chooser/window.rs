@@ -0,0 +1,153 @@
+#![allow(deprecated)] // GtkFileChooserWidget has no replacement that can be embedded
+
+use {
+    crate::gui::{
+        choices::ChoiceWidgets,
+        dialog_window,
+        file_chooser::{selected_uris, Choice, FileChooserUi, Filter, Filters},
+        set_parent_window, DialogOptions,
+    },
+    gtk4::{
+        gio::Cancellable,
+        glib::Propagation,
+        prelude::{BoxExt, ButtonExt, FileChooserExt, FileExt, GtkWindowExt, WidgetExt},
+        AlertDialog, Button, FileChooserAction, FileChooserWidget, Orientation, Window,
+    },
+    rust_i18n::t,
+    std::rc::Rc,
+};
+
+/// A file chooser that embeds a [`FileChooserWidget`] in a plain window.
+///
+/// Choices are shown below the file chooser using [`ChoiceWidgets`].
+#[derive(Clone)]
+pub struct Dialog {
+    window: Window,
+    chooser: FileChooserWidget,
+    accept: Button,
+    cancel: Button,
+    choices: Rc<ChoiceWidgets>,
+    filters: Rc<Filters>,
+}
+
+impl Dialog {
+    pub fn new(ui: &FileChooserUi, read_only: Option<&Choice>) -> Self {
+        let (window, cancel, accept) = dialog_window(&DialogOptions {
+            title: ui.title.clone(),
+            cancel_label: t!("_Cancel").into(),
+            accept_label: ui.accept_label(),
+            modal: ui.modal,
+            default_size: (800, 600),
+            parent_window: ui.parent_window.clone(),
+        });
+        let chooser = FileChooserWidget::new(ui.action());
+        chooser.set_vexpand(true);
+        let filters = ui.configure(&chooser);
+        let content = gtk4::Box::new(Orientation::Vertical, 0);
+        content.append(&chooser);
+        let choice_box = gtk4::Box::new(Orientation::Horizontal, 12);
+        choice_box.set_margin_top(6);
+        choice_box.set_margin_bottom(6);
+        choice_box.set_margin_start(12);
+        choice_box.set_margin_end(12);
+        let choices = ChoiceWidgets::new(
+            &choice_box,
+            read_only
+                .into_iter()
+                .chain(ui.choices.as_deref().unwrap_or_default()),
+        );
+        if !choices.is_empty() {
+            content.append(&choice_box);
+        }
+        window.set_child(Some(&content));
+        set_parent_window(&window, &ui.parent_window);
+        Self {
+            window,
+            chooser,
+            accept,
+            cancel,
+            choices: Rc::new(choices),
+            filters,
+        }
+    }
+
+    /// Calls `f` with `true` if the user accepted the dialog and `false` otherwise.
+    pub fn connect_response(&self, f: impl Fn(&Self, bool) + 'static) {
+        let f = Rc::new(f);
+        self.accept.connect_clicked({
+            let slf = self.clone();
+            let f = f.clone();
+            move |_| slf.accept(&f)
+        });
+        // Double-clicking a file does not accept the dialog: the widget only reports that
+        // through a private signal. Enter activates the accept button, which is the
+        // default widget of the window.
+        self.cancel.connect_clicked({
+            let window = self.window.clone();
+            move |_| window.close()
+        });
+        let slf = self.clone();
+        self.window.connect_close_request(move |_| {
+            f(&slf, false);
+            Propagation::Proceed
+        });
+    }
+
+    /// Responds with `true` unless the user does not want to replace an existing file.
+    fn accept(&self, f: &Rc<impl Fn(&Self, bool) + 'static>) {
+        let existing = match self.chooser.action() {
+            FileChooserAction::Save => self
+                .chooser
+                .file()
+                .filter(|f| f.query_exists(None::<&Cancellable>)),
+            _ => None,
+        };
+        let Some(file) = existing else {
+            f(self, true);
+            return;
+        };
+        let name = file.basename().unwrap_or_default();
+        let folder = file.parent().and_then(|p| p.basename()).unwrap_or_default();
+        let alert = AlertDialog::builder()
+            .modal(true)
+            .message(t!(
+                "A file named “%{name}” already exists. Do you want to replace it?",
+                name = name.display()
+            ))
+            .detail(t!(
+                "The file already exists in “%{folder}”. Replacing it will overwrite its contents.",
+                folder = folder.display()
+            ))
+            .buttons([t!("_Cancel").as_ref(), t!("_Replace").as_ref()])
+            .cancel_button(0)
+            .default_button(1)
+            .build();
+        let slf = self.clone();
+        let f = f.clone();
+        alert.choose(Some(&self.window), None::<&Cancellable>, move |res| {
+            if res == Ok(1) {
+                f(&slf, true);
+            }
+        });
+    }
+
+    pub fn files(&self) -> Vec<String> {
+        selected_uris(&self.chooser)
+    }
+
+    pub fn current_filter(&self) -> Option<Filter> {
+        self.filters.current()
+    }
+
+    pub fn choice(&self, id: &str) -> Option<String> {
+        self.choices.get(id)
+    }
+
+    pub fn present(&self) {
+        self.window.present();
+    }
+
+    pub fn close(&self) {
+        self.window.close();
+    }
+}
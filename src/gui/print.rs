@@ -0,0 +1,285 @@
+#[allow(deprecated)] // GtkDialog is deprecated since GTK 4.10
+use {
+    crate::{
+        gui::{set_parent_window, UiProxy},
+        utils::cancellation::Cancellation,
+    },
+    async_channel::Sender,
+    futures_util::{future::BoxFuture, select, FutureExt},
+    gtk4::{
+        glib::{self, thread_guard::ThreadGuard, MainContext, VariantDict},
+        prelude::{ButtonExt, Cast, DialogExt, GtkWindowExt, ToVariant},
+        Button, PrintCapabilities, PrintJob, PrintUnixDialog, Printer, ResponseType, Window,
+    },
+    std::{
+        collections::HashMap,
+        os::fd::{AsRawFd, OwnedFd},
+    },
+    thiserror::Error,
+};
+
+#[derive(Debug, Error)]
+pub enum PrintError {
+    #[error("Operation could not be started")]
+    Closed,
+    #[error("Operation was rejected")]
+    Rejected,
+    #[error("Operation was cancelled")]
+    Cancelled,
+    #[error("No printer was selected")]
+    NoPrinter,
+    #[error("Printer `{0}` does not exist")]
+    UnknownPrinter(String),
+    #[error("Could not read the document")]
+    Source(#[source] glib::Error),
+    #[error("Could not send the print job")]
+    Send(#[source] glib::Error),
+}
+
+/// A frontend that asks the user how to print and submits print jobs.
+pub trait PrintBackend: Send + Sync {
+    /// Shows a print dialog until the user responds or the operation is cancelled.
+    fn prepare(
+        &self,
+        ui: PrintUi,
+        cancellation: Cancellation,
+    ) -> BoxFuture<'static, Result<PrintSetup, PrintError>>;
+
+    /// Sends a document to the printer selected in its settings.
+    fn print(&self, document: PrintDocument) -> BoxFuture<'static, Result<(), PrintError>>;
+}
+
+impl PrintBackend for UiProxy {
+    fn prepare(
+        &self,
+        ui: PrintUi,
+        cancellation: Cancellation,
+    ) -> BoxFuture<'static, Result<PrintSetup, PrintError>> {
+        let proxy = self.clone();
+        async move { ui.run(&proxy, cancellation).await }.boxed()
+    }
+
+    fn print(&self, document: PrintDocument) -> BoxFuture<'static, Result<(), PrintError>> {
+        let proxy = self.clone();
+        async move { document.print(&proxy).await }.boxed()
+    }
+}
+
+/// The settings of a print job.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PrintSetup {
+    /// The keys and values of a `GtkPrintSettings`.
+    pub settings: HashMap<String, String>,
+    pub page_setup: PageSetup,
+}
+
+/// The serializable parts of a `GtkPageSetup`.
+///
+/// Sizes are in millimeters.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PageSetup {
+    pub ppd_name: Option<String>,
+    pub name: Option<String>,
+    pub display_name: Option<String>,
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    pub margin_top: Option<f64>,
+    pub margin_bottom: Option<f64>,
+    pub margin_left: Option<f64>,
+    pub margin_right: Option<f64>,
+    pub orientation: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct PrintUi {
+    pub title: String,
+    pub modal: bool,
+    pub accept_label: Option<String>,
+    pub setup: PrintSetup,
+    pub parent_window: String,
+}
+
+#[derive(Debug)]
+pub struct PrintDocument {
+    pub title: String,
+    pub fd: OwnedFd,
+    pub setup: PrintSetup,
+}
+
+impl PrintUi {
+    /// Shows the dialog until the user responds or the operation is cancelled.
+    ///
+    /// If the operation is cancelled, this function returns immediately and the dialog is
+    /// closed as soon as the main loop gets to it.
+    pub async fn run(
+        self,
+        proxy: &UiProxy,
+        cancellation: Cancellation,
+    ) -> Result<PrintSetup, PrintError> {
+        let (send, recv) = async_channel::bounded(1);
+        let context = proxy.context.clone();
+        let c = cancellation.clone();
+        proxy
+            .context
+            .invoke(move || self.run_impl(send, context, c));
+        select! {
+            res = recv.recv().fuse() => res.map_err(|_| PrintError::Closed)?,
+            _ = cancellation.cancelled().fuse() => Err(PrintError::Cancelled),
+        }
+    }
+
+    #[allow(deprecated)]
+    fn run_impl(
+        self,
+        send: Sender<Result<PrintSetup, PrintError>>,
+        context: MainContext,
+        cancellation: Cancellation,
+    ) {
+        if cancellation.is_cancelled() {
+            let _ = send.try_send(Err(PrintError::Cancelled));
+            return;
+        }
+        let dialog = PrintUnixDialog::new(Some(&self.title), Window::NONE);
+        dialog.set_modal(self.modal);
+        dialog.set_manual_capabilities(PrintCapabilities::GENERATE_PDF);
+        dialog.set_settings(Some(&gtk_settings(&self.setup.settings)));
+        dialog.set_page_setup(&gtk_page_setup(&self.setup.page_setup));
+        if let Some(label) = &self.accept_label {
+            let button = dialog
+                .widget_for_response(ResponseType::Ok)
+                .and_then(|w| w.downcast::<Button>().ok());
+            if let Some(button) = button {
+                button.set_label(label);
+                button.set_use_underline(true);
+            }
+        }
+        set_parent_window(&dialog, &self.parent_window);
+        let on_cancel = send.clone();
+        dialog.connect_response(move |dialog, r| {
+            let res = match r {
+                ResponseType::Ok => selected_setup(dialog),
+                _ => Err(PrintError::Rejected),
+            };
+            let _ = send.try_send(res);
+            dialog.close();
+        });
+        dialog.present();
+        context.spawn_local(async move {
+            cancellation.cancelled().await;
+            let _ = on_cancel.try_send(Err(PrintError::Cancelled));
+            dialog.close();
+        });
+    }
+}
+
+impl PrintDocument {
+    /// Sends the document to the printer named in the settings.
+    ///
+    /// The file descriptor is kept open until the job has been sent.
+    pub async fn print(self, proxy: &UiProxy) -> Result<(), PrintError> {
+        let (send, recv) = async_channel::bounded(1);
+        let context = proxy.context.clone();
+        proxy.context.invoke(move || {
+            context.spawn_local(async move {
+                let _ = send.send(self.send_job().await).await;
+            });
+        });
+        recv.recv().await.map_err(|_| PrintError::Closed)?
+    }
+
+    async fn send_job(self) -> Result<(), PrintError> {
+        let settings = gtk_settings(&self.setup.settings);
+        let name = settings.printer().ok_or(PrintError::NoPrinter)?.to_string();
+        let Some(printer) = find_printer(&name).await else {
+            return Err(PrintError::UnknownPrinter(name));
+        };
+        let page_setup = gtk_page_setup(&self.setup.page_setup);
+        let job = PrintJob::new(&self.title, &printer, &settings, &page_setup);
+        job.set_source_fd(self.fd.as_raw_fd())
+            .map_err(PrintError::Source)?;
+        let (send, recv) = async_channel::bounded(1);
+        job.send(move |_, res| {
+            let _ = send.try_send(res);
+        });
+        let res = recv.recv().await.map_err(|_| PrintError::Closed)?;
+        drop(self.fd);
+        res.map_err(PrintError::Send)
+    }
+}
+
+fn selected_setup(dialog: &PrintUnixDialog) -> Result<PrintSetup, PrintError> {
+    let printer = dialog.selected_printer().ok_or(PrintError::NoPrinter)?;
+    let settings = dialog.settings();
+    settings.set_printer(&printer.name());
+    Ok(PrintSetup {
+        settings: settings_map(&settings),
+        page_setup: page_setup(&dialog.page_setup()),
+    })
+}
+
+/// Returns the printer with the given name once the print backends have found it.
+async fn find_printer(name: &str) -> Option<Printer> {
+    let (send, recv) = async_channel::bounded(1);
+    let name = name.to_string();
+    gtk4::enumerate_printers(
+        move |printer| {
+            if printer.name() != name {
+                return false;
+            }
+            let _ = send.try_send(ThreadGuard::new(printer.clone()));
+            true
+        },
+        false,
+    );
+    recv.recv().await.ok().map(ThreadGuard::into_inner)
+}
+
+fn gtk_settings(settings: &HashMap<String, String>) -> gtk4::PrintSettings {
+    let res = gtk4::PrintSettings::new();
+    for (key, value) in settings {
+        res.set(key, Some(value));
+    }
+    res
+}
+
+fn settings_map(settings: &gtk4::PrintSettings) -> HashMap<String, String> {
+    let mut res = HashMap::new();
+    settings.foreach(|key, value| {
+        res.insert(key.to_string(), value.to_string());
+    });
+    res
+}
+
+macro_rules! page_setup_keys {
+    ($($field:ident: $key:literal,)*) => {
+        fn gtk_page_setup(setup: &PageSetup) -> gtk4::PageSetup {
+            let dict = VariantDict::new(None);
+            $(
+                if let Some(v) = &setup.$field {
+                    dict.insert_value($key, &v.to_variant());
+                }
+            )*
+            gtk4::PageSetup::from_gvariant(&dict.end())
+        }
+
+        fn page_setup(setup: &gtk4::PageSetup) -> PageSetup {
+            let dict = VariantDict::new(Some(&setup.to_gvariant()));
+            PageSetup {
+                $($field: dict.lookup($key).ok().flatten(),)*
+            }
+        }
+    };
+}
+
+page_setup_keys! {
+    ppd_name: "PPDName",
+    name: "Name",
+    display_name: "DisplayName",
+    width: "Width",
+    height: "Height",
+    margin_top: "MarginTop",
+    margin_bottom: "MarginBottom",
+    margin_left: "MarginLeft",
+    margin_right: "MarginRight",
+    orientation: "Orientation",
+}
@@ -0,0 +1,229 @@
+use {
+    crate::gui::UiProxy,
+    async_channel::{Receiver, Sender},
+    error_reporter::Report,
+    gtk4::{
+        gio::{
+            self,
+            prelude::{FileExt, FileMonitorExt},
+            Cancellable, FileMonitor, FileMonitorFlags,
+        },
+        glib::{self, FileError, KeyFile, KeyFileFlags, Variant, VariantClass},
+        prelude::ObjectExt,
+    },
+    std::{collections::HashMap, path::PathBuf, rc::Rc},
+    zbus::zvariant::{Array, OwnedValue, Signature, StructureBuilder, Value},
+};
+
+/// Setting values by namespace and key.
+pub type Namespaces = HashMap<String, HashMap<String, OwnedValue>>;
+
+const APPEARANCE: &str = "org.freedesktop.appearance";
+const INTERFACE: &str = "org.gnome.desktop.interface";
+
+/// The GTK settings that the values are derived from.
+const WATCHED_PROPERTIES: &[&str] = &[
+    "gtk-application-prefer-dark-theme",
+    "gtk-theme-name",
+    "gtk-icon-theme-name",
+    "gtk-cursor-theme-name",
+    "gtk-cursor-theme-size",
+    "gtk-font-name",
+    "gtk-enable-animations",
+];
+
+impl UiProxy {
+    /// Sends the current settings and then the new settings whenever they change.
+    ///
+    /// The settings are derived from the [`gtk4::Settings`] of the default display. Values
+    /// in the key file at `path` take precedence. Each group of the key file is a
+    /// namespace and each value is in the GVariant text format, e.g.
+    ///
+    /// ```ini
+    /// [org.freedesktop.appearance]
+    /// color-scheme=uint32 1
+    /// accent-color=(0.21, 0.52, 0.89)
+    /// ```
+    ///
+    /// GTK has no accent color, so `accent-color` is only published if it is set in the
+    /// key file.
+    pub fn watch_settings(&self, path: PathBuf) -> Receiver<Namespaces> {
+        let (send, recv) = async_channel::unbounded();
+        self.context.invoke(move || Watcher::start(send, path));
+        recv
+    }
+}
+
+struct Watcher {
+    settings: gtk4::Settings,
+    path: PathBuf,
+    /// Cancelled when it is dropped, so it lives as long as the watcher.
+    monitor: Option<FileMonitor>,
+    send: Sender<Namespaces>,
+}
+
+impl Watcher {
+    fn start(send: Sender<Namespaces>, path: PathBuf) {
+        let Some(settings) = gtk4::Settings::default() else {
+            log::error!("There is no default display to read settings from");
+            return;
+        };
+        let monitor = match gio::File::for_path(&path)
+            .monitor_file(FileMonitorFlags::NONE, Cancellable::NONE)
+        {
+            Ok(monitor) => Some(monitor),
+            Err(e) => {
+                log::warn!("Could not monitor {}: {}", path.display(), Report::new(e));
+                None
+            }
+        };
+        let watcher = Rc::new(Self {
+            settings,
+            path,
+            monitor,
+            send,
+        });
+        for property in WATCHED_PROPERTIES {
+            let watcher = watcher.clone();
+            watcher
+                .settings
+                .clone()
+                .connect_notify_local(Some(property), move |_, _| watcher.update());
+        }
+        if let Some(monitor) = &watcher.monitor {
+            let w = watcher.clone();
+            monitor.connect_changed(move |_, _, _, _| w.update());
+        }
+        watcher.update();
+    }
+
+    fn update(&self) {
+        let mut namespaces = gtk_settings(&self.settings);
+        if let Some(file) = load(&self.path) {
+            for (namespace, values) in parse(&file) {
+                namespaces.entry(namespace).or_default().extend(values);
+            }
+        }
+        let _ = self.send.try_send(namespaces);
+    }
+}
+
+fn gtk_settings(settings: &gtk4::Settings) -> Namespaces {
+    let dark = settings.is_gtk_application_prefer_dark_theme();
+    let theme = settings.gtk_theme_name().unwrap_or_default();
+    let high_contrast = theme.contains("HighContrast");
+    let mut appearance = HashMap::new();
+    appearance.insert("color-scheme".to_string(), OwnedValue::from(dark as u32));
+    appearance.insert(
+        "contrast".to_string(),
+        OwnedValue::from(high_contrast as u32),
+    );
+    let mut interface = HashMap::new();
+    let mut string = |key: &str, value: Option<glib::GString>| {
+        if let Some(value) = value {
+            interface.insert(key.to_string(), owned(Value::from(value.to_string())));
+        }
+    };
+    string("gtk-theme", Some(theme));
+    string("icon-theme", settings.gtk_icon_theme_name());
+    string("cursor-theme", settings.gtk_cursor_theme_name());
+    string("font-name", settings.gtk_font_name());
+    let color_scheme = match dark {
+        true => "prefer-dark",
+        false => "default",
+    };
+    interface.insert("color-scheme".to_string(), owned(Value::from(color_scheme)));
+    interface.insert(
+        "cursor-size".to_string(),
+        OwnedValue::from(settings.gtk_cursor_theme_size()),
+    );
+    interface.insert(
+        "enable-animations".to_string(),
+        OwnedValue::from(settings.is_gtk_enable_animations()),
+    );
+    Namespaces::from([
+        (APPEARANCE.to_string(), appearance),
+        (INTERFACE.to_string(), interface),
+    ])
+}
+
+/// Loads the key file if it exists.
+fn load(path: &std::path::Path) -> Option<KeyFile> {
+    let file = KeyFile::new();
+    match file.load_from_file(path, KeyFileFlags::NONE) {
+        Ok(()) => Some(file),
+        Err(e) if e.matches(FileError::Noent) => None,
+        Err(e) => {
+            log::error!("Could not load {}: {}", path.display(), Report::new(e));
+            None
+        }
+    }
+}
+
+/// Parses the values of a settings key file. Invalid values are ignored.
+pub fn parse(file: &KeyFile) -> Namespaces {
+    let mut res = Namespaces::new();
+    for namespace in file.groups() {
+        let namespace = namespace.to_string();
+        let Ok(keys) = file.keys(&namespace) else {
+            continue;
+        };
+        for key in keys {
+            let Ok(text) = file.value(&namespace, &key) else {
+                continue;
+            };
+            let value = Variant::parse(None, &text)
+                .map_err(|e| Report::new(e).to_string())
+                .and_then(|v| to_value(&v).ok_or_else(|| "unsupported type".to_string()));
+            match value {
+                Ok(value) => {
+                    res.entry(namespace.clone())
+                        .or_default()
+                        .insert(key.to_string(), owned(value));
+                }
+                Err(e) => log::warn!("Ignoring invalid value of {namespace}.{key}: {e}"),
+            }
+        }
+    }
+    res
+}
+
+fn owned(value: Value<'_>) -> OwnedValue {
+    value
+        .try_to_owned()
+        .expect("settings do not contain file descriptors")
+}
+
+/// Converts a GVariant without dictionaries, maybe types or handles.
+fn to_value(v: &Variant) -> Option<Value<'static>> {
+    let value = match v.classify() {
+        VariantClass::Boolean => Value::from(v.get::<bool>()?),
+        VariantClass::Byte => Value::from(v.get::<u8>()?),
+        VariantClass::Int16 => Value::from(v.get::<i16>()?),
+        VariantClass::Uint16 => Value::from(v.get::<u16>()?),
+        VariantClass::Int32 => Value::from(v.get::<i32>()?),
+        VariantClass::Uint32 => Value::from(v.get::<u32>()?),
+        VariantClass::Int64 => Value::from(v.get::<i64>()?),
+        VariantClass::Uint64 => Value::from(v.get::<u64>()?),
+        VariantClass::Double => Value::from(v.get::<f64>()?),
+        VariantClass::String => Value::from(v.str()?.to_string()),
+        VariantClass::Variant => Value::Value(Box::new(to_value(&v.as_variant()?)?)),
+        VariantClass::Tuple => {
+            let mut builder = StructureBuilder::new();
+            for child in v.iter() {
+                builder = builder.append_field(to_value(&child)?);
+            }
+            Value::from(builder.build())
+        }
+        VariantClass::Array => {
+            let signature = Signature::try_from(v.type_().element().as_str()).ok()?;
+            let mut array = Array::new(signature.to_owned());
+            for child in v.iter() {
+                array.append(to_value(&child)?).ok()?;
+            }
+            Value::from(array)
+        }
+        _ => return None,
+    };
+    Some(value)
+}
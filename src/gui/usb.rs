@@ -0,0 +1,125 @@
+use {
+    crate::gui::{app_name, centered_label, content_box, Dialog, DialogOptions, DialogUi},
+    gtk4::{
+        prelude::{BoxExt, ButtonExt, Cast, CheckButtonExt, WidgetExt},
+        CheckButton, Label, Orientation, Widget,
+    },
+    rust_i18n::t,
+    std::rc::Rc,
+};
+
+/// A device that an application wants to access.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UsbDevice {
+    pub id: String,
+    pub vendor: Option<String>,
+    pub product: Option<String>,
+    pub serial: Option<String>,
+    pub writable: bool,
+}
+
+impl UsbDevice {
+    fn title(&self) -> String {
+        match (&self.vendor, &self.product) {
+            (Some(vendor), Some(product)) => format!("{vendor} {product}"),
+            (Some(name), None) | (None, Some(name)) => name.clone(),
+            (None, None) => t!("Unknown Device").into_owned(),
+        }
+    }
+
+    fn details(&self) -> Vec<String> {
+        let mut details = vec![];
+        if let Some(serial) = &self.serial {
+            details.push(t!("Serial number %{serial}", serial = serial).into_owned());
+        }
+        let access = if self.writable {
+            t!("Read and write access")
+        } else {
+            t!("Read-only access")
+        };
+        details.push(access.into_owned());
+        details
+    }
+}
+
+/// Asks the user which USB devices an application may access.
+///
+/// Completes with the IDs of the devices that the user allowed.
+#[derive(Debug)]
+pub struct UsbUi {
+    pub app_id: String,
+    pub devices: Vec<UsbDevice>,
+    pub modal: bool,
+    pub parent_window: String,
+}
+
+impl DialogUi for UsbUi {
+    type Output = Vec<String>;
+
+    fn options(&self) -> DialogOptions {
+        DialogOptions {
+            title: t!("USB Devices").into_owned(),
+            cancel_label: t!("_Deny").into_owned(),
+            accept_label: t!("_Allow").into_owned(),
+            modal: self.modal,
+            default_size: (400, -1),
+            parent_window: self.parent_window.clone(),
+        }
+    }
+
+    fn build(self, dialog: &Dialog<Vec<String>>) -> Widget {
+        let content = content_box();
+        content.append(&centered_label(&self.heading()));
+        let list = gtk4::Box::new(Orientation::Vertical, 6);
+        let checks: Rc<Vec<(String, CheckButton)>> = Rc::new(
+            self.devices
+                .iter()
+                .map(|device| (device.id.clone(), device_row(&list, device)))
+                .collect(),
+        );
+        content.append(&list);
+        for (_, check) in checks.iter() {
+            check.connect_toggled({
+                let checks = checks.clone();
+                let accept = dialog.accept.clone();
+                move |_| accept.set_sensitive(checks.iter().any(|(_, c)| c.is_active()))
+            });
+        }
+        dialog.accept.connect_clicked({
+            let dialog = dialog.clone();
+            move |_| {
+                let ids = checks
+                    .iter()
+                    .filter(|(_, check)| check.is_active())
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                dialog.accept_with(ids);
+            }
+        });
+        content.upcast()
+    }
+}
+
+impl UsbUi {
+    fn heading(&self) -> String {
+        let app = app_name(&self.app_id);
+        if app.is_empty() {
+            return t!("An application wants to access USB devices.").into_owned();
+        }
+        t!("“%{app}” wants to access USB devices.", app = app).into_owned()
+    }
+}
+
+/// Appends a checked check button for the device to `list`.
+fn device_row(list: &gtk4::Box, device: &UsbDevice) -> CheckButton {
+    let check = CheckButton::with_label(&device.title());
+    check.set_active(true);
+    list.append(&check);
+    for detail in device.details() {
+        let detail = Label::builder().label(detail).xalign(0.0).build();
+        detail.set_margin_start(28);
+        detail.add_css_class("dim-label");
+        list.append(&detail);
+    }
+    check
+}
@@ -0,0 +1,77 @@
+use {
+    crate::gui::{app_name, centered_label, content_box, Dialog, DialogOptions, DialogUi},
+    gtk4::{
+        prelude::{BoxExt, ButtonExt, Cast, WidgetExt},
+        Label, Picture, Widget,
+    },
+    rust_i18n::t,
+    std::path::PathBuf,
+};
+
+/// Where the wallpaper is set.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SetOn {
+    Background,
+    Lockscreen,
+    Both,
+}
+
+#[derive(Debug)]
+pub struct PreviewUi {
+    pub app_id: String,
+    pub path: PathBuf,
+    pub set_on: SetOn,
+    pub modal: bool,
+    pub parent_window: String,
+}
+
+impl DialogUi for PreviewUi {
+    type Output = ();
+
+    fn options(&self) -> DialogOptions {
+        DialogOptions {
+            title: t!("Set Wallpaper").into_owned(),
+            cancel_label: t!("_Cancel").into_owned(),
+            accept_label: t!("_Set").into_owned(),
+            modal: self.modal,
+            default_size: (560, -1),
+            parent_window: self.parent_window.clone(),
+        }
+    }
+
+    fn build(self, dialog: &Dialog<()>) -> Widget {
+        let content = content_box();
+        content.append(&centered_label(&self.heading()));
+        let target = Label::new(Some(&self.target()));
+        target.add_css_class("dim-label");
+        content.append(&target);
+        let picture = Picture::for_filename(&self.path);
+        picture.set_can_shrink(true);
+        picture.set_size_request(480, 270);
+        content.append(&picture);
+        dialog.accept.connect_clicked({
+            let dialog = dialog.clone();
+            move |_| dialog.accept_with(())
+        });
+        content.upcast()
+    }
+}
+
+impl PreviewUi {
+    fn heading(&self) -> String {
+        let app = app_name(&self.app_id);
+        if app.is_empty() {
+            return t!("An application wants to change the wallpaper.").into_owned();
+        }
+        t!("“%{app}” wants to change the wallpaper.", app = app).into_owned()
+    }
+
+    fn target(&self) -> String {
+        match self.set_on {
+            SetOn::Background => t!("Background"),
+            SetOn::Lockscreen => t!("Lock Screen"),
+            SetOn::Both => t!("Background and Lock Screen"),
+        }
+        .into_owned()
+    }
+}
@@ -1,6 +1,7 @@
 use error_reporter::Report;
 
 mod cli;
+mod config;
 mod gui;
 mod logging;
 mod portal;
@@ -1,7 +1,16 @@
 use {
-    crate::gui::UiProxy,
-    portals::file_chooser::FileChooser,
-    std::thread,
+    crate::{
+        config::{self, ConfigHandle},
+        gui::UiProxy,
+        utils::wallpaper::Setters,
+    },
+    portals::{
+        access::Access, account::Account, app_chooser::AppChooser, background::Background,
+        dynamic_launcher::DynamicLauncher, email::Email, file_chooser::FileChooser,
+        inhibit::Inhibit, print::Print, screenshot::Screenshot, secret::Secret, usb::Usb,
+        wallpaper::Wallpaper,
+    },
+    std::{sync::Arc, thread},
     thiserror::Error,
     zbus::{
         blocking::{fdo::DBusProxy as DBusProxyBlocking, Connection},
@@ -12,6 +21,9 @@ use {
 mod portals;
 mod request;
 mod response;
+mod session;
+#[cfg(test)]
+mod tests;
 
 const NAME: &str = "org.freedesktop.impl.portal.desktop.gtk4";
 const PATH: &str = "/org/freedesktop/portal/desktop";
@@ -35,18 +47,13 @@ pub struct Portal {
 }
 
 impl Portal {
-    pub fn create(proxy: &UiProxy, replace: bool) -> Result<Self, PortalError> {
+    pub fn create(
+        proxy: &UiProxy,
+        config: &ConfigHandle,
+        replace: bool,
+    ) -> Result<Self, PortalError> {
         let session = Connection::session().map_err(PortalError::Connection)?;
-
-        macro_rules! add {
-            ($interface:expr) => {
-                session
-                    .object_server()
-                    .at(PATH, $interface)
-                    .map_err(PortalError::AddInterface)?;
-            };
-        }
-        add!(FileChooser::new(proxy));
+        add_interfaces(&session, proxy, config)?;
 
         let mut name_lost_iterator = DBusProxyBlocking::new(&session)
             .map_err(PortalError::CreateDbusProxy)?
@@ -68,3 +75,40 @@ impl Portal {
         Ok(Self { _session: session })
     }
 }
+
+fn add_interfaces(
+    connection: &Connection,
+    proxy: &UiProxy,
+    config: &ConfigHandle,
+) -> Result<(), PortalError> {
+    macro_rules! add {
+        ($interface:expr) => {
+            connection
+                .object_server()
+                .at(PATH, $interface)
+                .map_err(PortalError::AddInterface)?;
+        };
+    }
+    add!(Access::new(Arc::new(proxy.clone()), config));
+    add!(Account::new(Arc::new(proxy.clone()), config));
+    add!(AppChooser::new(Arc::new(proxy.clone()), config));
+    add!(Background::new(Arc::new(proxy.clone())));
+    add!(DynamicLauncher::new(Arc::new(proxy.clone()), config));
+    add!(Email::new(Arc::new(proxy.clone()), config));
+    add!(FileChooser::new(Arc::new(proxy.clone()), config));
+    add!(Inhibit::default());
+    add!(Print::new(Arc::new(proxy.clone()), config));
+    add!(Screenshot::new(Arc::new(proxy.clone()), config));
+    add!(Secret);
+    add!(Usb::new(Arc::new(proxy.clone()), config));
+    add!(Wallpaper::new(
+        Arc::new(proxy.clone()),
+        Arc::new(Setters::default()),
+        config
+    ));
+    let settings = proxy.watch_settings(config::settings_path());
+    portals::settings::serve(connection, settings).map_err(PortalError::AddInterface)?;
+    portals::lockdown::serve(connection, config).map_err(PortalError::AddInterface)?;
+    portals::notification::serve(connection).map_err(PortalError::AddInterface)?;
+    Ok(())
+}
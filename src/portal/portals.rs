@@ -1 +1,16 @@
+pub mod access;
+pub mod account;
+pub mod app_chooser;
+pub mod background;
+pub mod dynamic_launcher;
+pub mod email;
 pub mod file_chooser;
+pub mod inhibit;
+pub mod lockdown;
+pub mod notification;
+pub mod print;
+pub mod screenshot;
+pub mod secret;
+pub mod settings;
+pub mod usb;
+pub mod wallpaper;
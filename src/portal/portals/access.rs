@@ -0,0 +1,119 @@
+use {
+    crate::{
+        config::ConfigHandle,
+        gui::{access::AccessUi, DialogBackend, DialogError},
+        portal::{
+            portals::file_chooser::{map_choices, map_final_choices, Choice},
+            request::run_request,
+            response::Response,
+        },
+        utils::cancellation::Cancellation,
+    },
+    error_reporter::Report,
+    std::sync::Arc,
+    zbus::{
+        interface,
+        zvariant::{DeserializeDict, OwnedObjectPath, SerializeDict, Type},
+        Connection,
+    },
+};
+
+pub struct Access {
+    backend: Arc<dyn DialogBackend<AccessUi>>,
+    config: ConfigHandle,
+}
+
+impl Access {
+    pub fn new(backend: Arc<dyn DialogBackend<AccessUi>>, config: &ConfigHandle) -> Self {
+        Self {
+            backend,
+            config: config.clone(),
+        }
+    }
+}
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct AccessDialogOptions {
+    modal: Option<bool>,
+    deny_label: Option<String>,
+    grant_label: Option<String>,
+    icon: Option<String>,
+    choices: Option<Vec<Choice>>,
+}
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct AccessDialogResults {
+    choices: Option<Vec<(String, String)>>,
+}
+
+/// The texts of an access dialog.
+struct Texts {
+    title: String,
+    subtitle: String,
+    body: String,
+}
+
+impl Access {
+    async fn access_dialog_impl(
+        &self,
+        app_id: String,
+        parent_window: String,
+        texts: Texts,
+        options: AccessDialogOptions,
+        cancellation: Cancellation,
+    ) -> Response<AccessDialogResults> {
+        let ui = AccessUi {
+            app_id,
+            title: texts.title,
+            subtitle: texts.subtitle,
+            body: texts.body,
+            deny_label: options.deny_label,
+            grant_label: options.grant_label,
+            icon: options.icon,
+            choices: options.choices.map(map_choices).unwrap_or_default(),
+            modal: options.modal.unwrap_or(self.config.get().modal),
+            parent_window,
+        };
+        match self.backend.run(ui, cancellation).await {
+            Ok(res) => Response::success(AccessDialogResults {
+                choices: Some(map_final_choices(res.final_choices)),
+            }),
+            Err(e @ (DialogError::Rejected | DialogError::Cancelled)) => {
+                log::debug!("Access was not granted: {}", Report::new(e));
+                Response::cancelled()
+            }
+            Err(e) => {
+                log::error!("AccessDialog failed: {}", Report::new(e));
+                Response::failed()
+            }
+        }
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Access")]
+impl Access {
+    #[allow(clippy::too_many_arguments)]
+    async fn access_dialog(
+        &self,
+        handle: OwnedObjectPath,
+        app_id: String,
+        parent_window: String,
+        title: String,
+        subtitle: String,
+        body: String,
+        options: AccessDialogOptions,
+        #[zbus(connection)] conn: &Connection,
+    ) -> Response<AccessDialogResults> {
+        let texts = Texts {
+            title,
+            subtitle,
+            body,
+        };
+        run_request(conn, handle, |cancellation| {
+            self.access_dialog_impl(app_id, parent_window, texts, options, cancellation)
+        })
+        .await
+    }
+}
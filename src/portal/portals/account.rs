@@ -0,0 +1,156 @@
+use {
+    crate::{
+        config::ConfigHandle,
+        gui::{
+            account::{AccountUi, UserInfo},
+            DialogBackend, DialogError,
+        },
+        portal::{request::run_request, response::Response},
+        utils::cancellation::Cancellation,
+    },
+    error_reporter::Report,
+    std::{
+        ffi::{CStr, OsStr},
+        os::unix::ffi::OsStrExt,
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+    url::Url,
+    zbus::{
+        interface,
+        zvariant::{DeserializeDict, OwnedObjectPath, SerializeDict, Type},
+        Connection,
+    },
+};
+
+const ACCOUNTS_SERVICE_ICONS: &str = "/var/lib/AccountsService/icons";
+
+pub struct Account {
+    backend: Arc<dyn DialogBackend<AccountUi>>,
+    config: ConfigHandle,
+}
+
+impl Account {
+    pub fn new(backend: Arc<dyn DialogBackend<AccountUi>>, config: &ConfigHandle) -> Self {
+        Self {
+            backend,
+            config: config.clone(),
+        }
+    }
+}
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct GetUserInformationOptions {
+    reason: Option<String>,
+    modal: Option<bool>,
+}
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct GetUserInformationResults {
+    id: Option<String>,
+    name: Option<String>,
+    image: Option<String>,
+}
+
+impl Account {
+    async fn get_user_information_impl(
+        &self,
+        app_id: String,
+        parent_window: String,
+        options: GetUserInformationOptions,
+        cancellation: Cancellation,
+    ) -> Response<GetUserInformationResults> {
+        let Some(user) = current_user(Path::new(ACCOUNTS_SERVICE_ICONS)) else {
+            log::error!("Could not look up the current user");
+            return Response::failed();
+        };
+        let ui = AccountUi {
+            app_id,
+            reason: options.reason,
+            user: user.clone(),
+            modal: options.modal.unwrap_or(self.config.get().modal),
+            parent_window,
+        };
+        match self.backend.run(ui, cancellation).await {
+            Ok(()) => Response::success(GetUserInformationResults {
+                id: Some(user.id),
+                name: Some(user.name),
+                image: user
+                    .image
+                    .and_then(|p| Url::from_file_path(p).ok())
+                    .map(|u| u.to_string()),
+            }),
+            Err(e @ (DialogError::Rejected | DialogError::Cancelled)) => {
+                log::debug!("GetUserInformation was not confirmed: {}", Report::new(e));
+                Response::cancelled()
+            }
+            Err(e) => {
+                log::error!("GetUserInformation failed: {}", Report::new(e));
+                Response::failed()
+            }
+        }
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Account")]
+impl Account {
+    async fn get_user_information(
+        &self,
+        handle: OwnedObjectPath,
+        app_id: String,
+        window: String,
+        options: GetUserInformationOptions,
+        #[zbus(connection)] conn: &Connection,
+    ) -> Response<GetUserInformationResults> {
+        run_request(conn, handle, |cancellation| {
+            self.get_user_information_impl(app_id, window, options, cancellation)
+        })
+        .await
+    }
+}
+
+/// Looks up the current user in the password database.
+///
+/// The name is the first field of the GECOS field or the login name if that is empty.
+/// The image is taken from AccountsService or `~/.face`, whichever exists.
+pub fn current_user(icons: &Path) -> Option<UserInfo> {
+    let mut pwd = unsafe { std::mem::zeroed::<libc::passwd>() };
+    let mut buf = vec![0; 4096];
+    let mut res = std::ptr::null_mut();
+    loop {
+        let err = unsafe {
+            libc::getpwuid_r(
+                libc::getuid(),
+                &mut pwd,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut res,
+            )
+        };
+        if err != libc::ERANGE {
+            break;
+        }
+        buf.resize(buf.len() * 2, 0);
+    }
+    if res.is_null() {
+        return None;
+    }
+    let field = |p: *const libc::c_char| match p.is_null() {
+        true => &[][..],
+        false => unsafe { CStr::from_ptr(p) }.to_bytes(),
+    };
+    let id = String::from_utf8_lossy(field(pwd.pw_name)).into_owned();
+    let gecos = field(pwd.pw_gecos);
+    let name = gecos.split(|&b| b == b',').next().unwrap_or_default();
+    let name = match name.is_empty() {
+        true => id.clone(),
+        false => String::from_utf8_lossy(name).into_owned(),
+    };
+    let home = PathBuf::from(OsStr::from_bytes(field(pwd.pw_dir)));
+    let image = [icons.join(&id), home.join(".face")]
+        .into_iter()
+        .find(|p| p.is_file());
+    Some(UserInfo { id, name, image })
+}
@@ -0,0 +1,144 @@
+use {
+    crate::{
+        config::ConfigHandle,
+        gui::{app_chooser::AppChooserUi, DialogBackend, DialogError},
+        portal::{request::run_request, response::Response},
+        utils::cancellation::Cancellation,
+    },
+    async_channel::{Receiver, Sender},
+    error_reporter::Report,
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    },
+    zbus::{
+        interface,
+        zvariant::{DeserializeDict, OwnedObjectPath, SerializeDict, Type},
+        Connection,
+    },
+};
+
+type Updates = Mutex<HashMap<OwnedObjectPath, Sender<Vec<String>>>>;
+
+pub struct AppChooser {
+    backend: Arc<dyn DialogBackend<AppChooserUi>>,
+    config: ConfigHandle,
+    updates: Updates,
+}
+
+impl AppChooser {
+    pub fn new(backend: Arc<dyn DialogBackend<AppChooserUi>>, config: &ConfigHandle) -> Self {
+        Self {
+            backend,
+            config: config.clone(),
+            updates: Default::default(),
+        }
+    }
+}
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct ChooseApplicationOptions {
+    last_choice: Option<String>,
+    modal: Option<bool>,
+    content_type: Option<String>,
+    uri: Option<String>,
+    filename: Option<String>,
+}
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct ChooseApplicationResults {
+    choice: Option<String>,
+}
+
+impl AppChooser {
+    async fn choose_application_impl(
+        &self,
+        parent_window: String,
+        choices: Vec<String>,
+        options: ChooseApplicationOptions,
+        updates: Receiver<Vec<String>>,
+        cancellation: Cancellation,
+    ) -> Response<ChooseApplicationResults> {
+        let ui = AppChooserUi {
+            choices,
+            last_choice: options.last_choice,
+            modal: options.modal.unwrap_or(self.config.get().modal),
+            content_type: options.content_type,
+            uri: options.uri,
+            filename: options.filename,
+            parent_window,
+            updates,
+        };
+        match self.backend.run(ui, cancellation).await {
+            Ok(res) => Response::success(ChooseApplicationResults {
+                choice: Some(res.choice),
+            }),
+            Err(e @ (DialogError::Rejected | DialogError::Cancelled)) => {
+                log::debug!("ChooseApplication was not confirmed: {}", Report::new(e));
+                Response::cancelled()
+            }
+            Err(e) => {
+                log::error!("ChooseApplication failed: {}", Report::new(e));
+                Response::failed()
+            }
+        }
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.AppChooser")]
+impl AppChooser {
+    async fn choose_application(
+        &self,
+        handle: OwnedObjectPath,
+        _app_id: String,
+        parent_window: String,
+        choices: Vec<String>,
+        options: ChooseApplicationOptions,
+        #[zbus(connection)] conn: &Connection,
+    ) -> Response<ChooseApplicationResults> {
+        let (send, recv) = async_channel::unbounded();
+        let _registered = Registered::new(&self.updates, &handle, send);
+        run_request(conn, handle, |cancellation| {
+            self.choose_application_impl(parent_window, choices, options, recv, cancellation)
+        })
+        .await
+    }
+
+    async fn update_choices(&self, handle: OwnedObjectPath, choices: Vec<String>) {
+        let send = self.updates.lock().unwrap().get(&handle).cloned();
+        match send {
+            Some(send) => {
+                let _ = send.send(choices).await;
+            }
+            None => log::warn!(
+                "UpdateChoices called for unknown request {}",
+                handle.as_str()
+            ),
+        }
+    }
+}
+
+/// The update channel of a running request. It is unregistered when dropped, even if the
+/// request is abandoned before it completes.
+struct Registered<'a> {
+    updates: &'a Updates,
+    handle: OwnedObjectPath,
+}
+
+impl<'a> Registered<'a> {
+    fn new(updates: &'a Updates, handle: &OwnedObjectPath, send: Sender<Vec<String>>) -> Self {
+        updates.lock().unwrap().insert(handle.clone(), send);
+        Self {
+            updates,
+            handle: handle.clone(),
+        }
+    }
+}
+
+impl Drop for Registered<'_> {
+    fn drop(&mut self) {
+        self.updates.lock().unwrap().remove(&self.handle);
+    }
+}
@@ -0,0 +1,202 @@
+use {
+    crate::{
+        gui::{background::BackgroundUi, DialogBackend, DialogError},
+        portal::{request::run_request, response::Response},
+        utils::cancellation::Cancellation,
+    },
+    error_reporter::Report,
+    gtk4::{
+        gio::Application,
+        glib::{self, KeyFile},
+    },
+    std::{collections::HashMap, fs, io, path::PathBuf, sync::Arc},
+    thiserror::Error,
+    zbus::{
+        interface,
+        zvariant::{OwnedObjectPath, OwnedValue, SerializeDict, Type},
+        Connection,
+    },
+};
+
+const RESULT_FORBID: u32 = 0;
+const RESULT_ALLOW: u32 = 1;
+
+const AUTOSTART_DBUS_ACTIVATABLE: u32 = 1;
+
+#[derive(Debug, Error)]
+enum AutostartError {
+    #[error("{0:?} is not a valid application ID")]
+    InvalidAppId(String),
+    #[error("Could not create {}", .0.display())]
+    CreateDir(PathBuf, #[source] io::Error),
+    #[error("Could not write {}", .0.display())]
+    Write(PathBuf, #[source] io::Error),
+    #[error("Could not remove {}", .0.display())]
+    Remove(PathBuf, #[source] io::Error),
+}
+
+pub struct Background {
+    backend: Arc<dyn DialogBackend<BackgroundUi>>,
+    autostart_dir: PathBuf,
+}
+
+impl Background {
+    pub fn new(backend: Arc<dyn DialogBackend<BackgroundUi>>) -> Self {
+        Self::with_autostart_dir(backend, glib::user_config_dir().join("autostart"))
+    }
+
+    pub fn with_autostart_dir(
+        backend: Arc<dyn DialogBackend<BackgroundUi>>,
+        autostart_dir: PathBuf,
+    ) -> Self {
+        Self {
+            backend,
+            autostart_dir,
+        }
+    }
+}
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct NotifyBackgroundResults {
+    result: Option<u32>,
+}
+
+impl Background {
+    async fn notify_background_impl(
+        &self,
+        app_id: String,
+        name: String,
+        cancellation: Cancellation,
+    ) -> Response<NotifyBackgroundResults> {
+        let ui = BackgroundUi { app_id, name };
+        match self.backend.run(ui, cancellation).await {
+            Ok(allow) => Response::success(NotifyBackgroundResults {
+                result: Some(if allow { RESULT_ALLOW } else { RESULT_FORBID }),
+            }),
+            Err(DialogError::Rejected) => Response::success(NotifyBackgroundResults {
+                result: Some(RESULT_FORBID),
+            }),
+            Err(e @ DialogError::Cancelled) => {
+                log::debug!("NotifyBackground was not answered: {}", Report::new(e));
+                Response::cancelled()
+            }
+            Err(e) => {
+                log::error!("NotifyBackground failed: {}", Report::new(e));
+                Response::failed()
+            }
+        }
+    }
+
+    fn enable_autostart_impl(
+        &self,
+        app_id: &str,
+        enable: bool,
+        commandline: &[String],
+        flags: u32,
+    ) -> Result<(), AutostartError> {
+        if !Application::id_is_valid(app_id) {
+            return Err(AutostartError::InvalidAppId(app_id.to_string()));
+        }
+        let path = self.autostart_dir.join(format!("{app_id}.desktop"));
+        if !enable {
+            return match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    Err(AutostartError::Remove(path, e))
+                }
+                _ => Ok(()),
+            };
+        }
+        fs::create_dir_all(&self.autostart_dir)
+            .map_err(|e| AutostartError::CreateDir(self.autostart_dir.clone(), e))?;
+        let data = autostart_file(app_id, commandline, flags);
+        fs::write(&path, data).map_err(|e| AutostartError::Write(path, e))
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Background")]
+impl Background {
+    /// Window tracking is not implemented, so no application is reported as active.
+    async fn get_app_state(&self) -> HashMap<String, OwnedValue> {
+        HashMap::new()
+    }
+
+    async fn notify_background(
+        &self,
+        handle: OwnedObjectPath,
+        app_id: String,
+        name: String,
+        #[zbus(connection)] conn: &Connection,
+    ) -> Response<NotifyBackgroundResults> {
+        run_request(conn, handle, |cancellation| {
+            self.notify_background_impl(app_id, name, cancellation)
+        })
+        .await
+    }
+
+    /// Writes or removes `<app_id>.desktop` in the autostart directory.
+    async fn enable_autostart(
+        &self,
+        app_id: String,
+        enable: bool,
+        commandline: Vec<String>,
+        flags: u32,
+    ) -> bool {
+        match self.enable_autostart_impl(&app_id, enable, &commandline, flags) {
+            Ok(()) => enable,
+            Err(e) => {
+                log::error!("Could not update the autostart file: {}", Report::new(e));
+                false
+            }
+        }
+    }
+}
+
+fn autostart_file(app_id: &str, commandline: &[String], flags: u32) -> String {
+    const GROUP: &str = "Desktop Entry";
+    let file = KeyFile::new();
+    file.set_string(GROUP, "Type", "Application");
+    file.set_string(GROUP, "Name", app_id);
+    file.set_string(GROUP, "Exec", &exec(&flatpak_run(app_id, commandline)));
+    if flags & AUTOSTART_DBUS_ACTIVATABLE != 0 {
+        file.set_boolean(GROUP, "DBusActivatable", true);
+    }
+    file.set_string(GROUP, "X-Flatpak", app_id);
+    file.to_data().to_string()
+}
+
+/// Wraps the command line of a sandboxed app in `flatpak run`.
+fn flatpak_run(app_id: &str, commandline: &[String]) -> Vec<String> {
+    let mut argv = vec!["flatpak".to_string(), "run".to_string()];
+    let args = match commandline.split_first() {
+        Some((command, args)) => {
+            argv.push(format!("--command={command}"));
+            args
+        }
+        None => &[],
+    };
+    argv.push(app_id.to_string());
+    argv.extend_from_slice(args);
+    argv
+}
+
+/// Quotes the arguments of a command for the `Exec` key of a desktop file.
+fn exec(commandline: &[String]) -> String {
+    const RESERVED: &str = " \t\n\"'\\><~|&;$*?#()`";
+    let quote = |arg: &String| {
+        let arg = arg.replace('%', "%%");
+        if !arg.is_empty() && !arg.contains(|c| RESERVED.contains(c)) {
+            return arg;
+        }
+        let mut quoted = String::from('"');
+        for c in arg.chars() {
+            if matches!(c, '"' | '`' | '$' | '\\') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    };
+    commandline.iter().map(quote).collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,140 @@
+use {
+    crate::{
+        config::ConfigHandle,
+        gui::{dynamic_launcher::LauncherUi, DialogBackend, DialogError},
+        portal::{request::run_request, response::Response},
+        utils::{cancellation::Cancellation, icon::Icon},
+    },
+    error_reporter::Report,
+    std::{collections::HashMap, sync::Arc},
+    zbus::{
+        interface,
+        zvariant::{DeserializeDict, OwnedObjectPath, OwnedValue, SerializeDict, Type},
+        Connection,
+    },
+};
+
+const LAUNCHER_APPLICATION: u32 = 1;
+const LAUNCHER_WEBAPP: u32 = 2;
+
+pub struct DynamicLauncher {
+    backend: Arc<dyn DialogBackend<LauncherUi>>,
+    config: ConfigHandle,
+}
+
+impl DynamicLauncher {
+    pub fn new(backend: Arc<dyn DialogBackend<LauncherUi>>, config: &ConfigHandle) -> Self {
+        Self {
+            backend,
+            config: config.clone(),
+        }
+    }
+}
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct PrepareInstallOptions {
+    modal: Option<bool>,
+    launcher_type: Option<u32>,
+    target: Option<String>,
+    editable_name: Option<bool>,
+}
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct PrepareInstallResults {
+    name: Option<String>,
+    icon: Option<OwnedValue>,
+}
+
+/// The arguments of `PrepareInstall` that describe the launcher.
+struct Launcher {
+    name: String,
+    icon: OwnedValue,
+}
+
+impl DynamicLauncher {
+    async fn prepare_install_impl(
+        &self,
+        app_id: String,
+        parent_window: String,
+        launcher: Launcher,
+        options: PrepareInstallOptions,
+        cancellation: Cancellation,
+    ) -> Response<PrepareInstallResults> {
+        let target = match options.launcher_type {
+            Some(LAUNCHER_WEBAPP) => options.target,
+            _ => None,
+        };
+        let ui = LauncherUi {
+            app_id,
+            name: launcher.name,
+            icon: Icon::parse(&launcher.icon),
+            target,
+            editable_name: options.editable_name.unwrap_or(true),
+            modal: options.modal.unwrap_or(self.config.get().modal),
+            parent_window,
+        };
+        match self.backend.run(ui, cancellation).await {
+            Ok(name) => Response::success(PrepareInstallResults {
+                name: Some(name),
+                icon: Some(launcher.icon),
+            }),
+            Err(e @ (DialogError::Rejected | DialogError::Cancelled)) => {
+                log::debug!("PrepareInstall was not confirmed: {}", Report::new(e));
+                Response::cancelled()
+            }
+            Err(e) => {
+                log::error!("PrepareInstall failed: {}", Report::new(e));
+                Response::failed()
+            }
+        }
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.DynamicLauncher")]
+impl DynamicLauncher {
+    #[allow(clippy::too_many_arguments)]
+    async fn prepare_install(
+        &self,
+        handle: OwnedObjectPath,
+        app_id: String,
+        parent_window: String,
+        name: String,
+        icon: OwnedValue,
+        options: PrepareInstallOptions,
+        #[zbus(connection)] conn: &Connection,
+    ) -> Response<PrepareInstallResults> {
+        let launcher = Launcher { name, icon };
+        run_request(conn, handle, |cancellation| {
+            self.prepare_install_impl(app_id, parent_window, launcher, options, cancellation)
+        })
+        .await
+    }
+
+    /// Allows the apps listed in the configuration to install launchers without a
+    /// dialog.
+    async fn request_install_token(
+        &self,
+        app_id: String,
+        _options: HashMap<String, OwnedValue>,
+    ) -> u32 {
+        let config = self.config.get();
+        if config.dynamic_launcher.install_token_apps.contains(&app_id) {
+            Response::success(()).code()
+        } else {
+            log::debug!("{app_id} may not install launchers without a dialog");
+            Response::<()>::failed().code()
+        }
+    }
+
+    #[zbus(property)]
+    fn supported_launcher_types(&self) -> u32 {
+        LAUNCHER_APPLICATION | LAUNCHER_WEBAPP
+    }
+
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        1
+    }
+}
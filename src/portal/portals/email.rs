@@ -0,0 +1,155 @@
+use {
+    crate::{
+        config::ConfigHandle,
+        gui::email::{Draft, MailClient},
+        portal::{request::run_request, response::Response},
+    },
+    error_reporter::Report,
+    gtk4::glib::Uri,
+    std::{
+        fs::{self, File},
+        io,
+        os::{
+            fd::{AsRawFd, OwnedFd},
+            unix::{ffi::OsStrExt, fs::MetadataExt},
+        },
+        path::PathBuf,
+        sync::Arc,
+    },
+    zbus::{
+        interface,
+        zvariant::{self, DeserializeDict, OwnedObjectPath, SerializeDict, Type},
+        Connection,
+    },
+};
+
+pub struct Email {
+    client: Arc<dyn MailClient>,
+    config: ConfigHandle,
+}
+
+impl Email {
+    pub fn new(client: Arc<dyn MailClient>, config: &ConfigHandle) -> Self {
+        Self {
+            client,
+            config: config.clone(),
+        }
+    }
+}
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct ComposeEmailOptions {
+    address: Option<String>,
+    addresses: Option<Vec<String>>,
+    cc: Option<Vec<String>>,
+    bcc: Option<Vec<String>>,
+    subject: Option<String>,
+    body: Option<String>,
+    attachment_fds: Option<Vec<zvariant::OwnedFd>>,
+    activation_token: Option<String>,
+}
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct ComposeEmailResults {}
+
+impl Email {
+    async fn compose_email_impl(
+        &self,
+        options: ComposeEmailOptions,
+    ) -> Response<ComposeEmailResults> {
+        let mut attachments = vec![];
+        for fd in options.attachment_fds.unwrap_or_default() {
+            match attachment_path(fd.into()) {
+                Ok(path) => attachments.push(path),
+                Err(e) => {
+                    log::error!(
+                        "Could not determine the attachment path: {}",
+                        Report::new(e)
+                    );
+                    return Response::failed();
+                }
+            }
+        }
+        let to: Vec<_> = options
+            .address
+            .iter()
+            .chain(options.addresses.iter().flatten())
+            .map(|a| escape(a))
+            .collect();
+        let mut query = vec![];
+        for (name, values) in [("cc", &options.cc), ("bcc", &options.bcc)] {
+            for value in values.iter().flatten() {
+                query.push((name, escape(value)));
+            }
+        }
+        for (name, value) in [("subject", &options.subject), ("body", &options.body)] {
+            if let Some(value) = value {
+                query.push((name, escape(value)));
+            }
+        }
+        for path in &attachments {
+            let path = Uri::escape_bytes(path.as_os_str().as_bytes(), Some("/"));
+            query.push(("attachment", path.to_string()));
+        }
+        let draft = Draft {
+            uri: mailto(&to, &query),
+            command: self.config.get().email.command.clone(),
+            activation_token: options.activation_token,
+        };
+        match self.client.compose(draft).await {
+            Ok(()) => Response::success(ComposeEmailResults::default()),
+            Err(e) => {
+                log::error!("Could not start the mail client: {}", Report::new(e));
+                Response::failed()
+            }
+        }
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Email")]
+impl Email {
+    async fn compose_email(
+        &self,
+        handle: OwnedObjectPath,
+        _app_id: String,
+        _parent_window: String,
+        options: ComposeEmailOptions,
+        #[zbus(connection)] conn: &Connection,
+    ) -> Response<ComposeEmailResults> {
+        run_request(conn, handle, |_| self.compose_email_impl(options)).await
+    }
+}
+
+fn escape(s: &str) -> String {
+    Uri::escape_string(s, Some("@"), false).to_string()
+}
+
+/// Builds a `mailto:` URI from escaped recipients and query values.
+fn mailto(to: &[String], query: &[(&str, String)]) -> String {
+    let mut uri = format!("mailto:{}", to.join(","));
+    for (i, (name, value)) in query.iter().enumerate() {
+        uri.push(if i == 0 { '?' } else { '&' });
+        uri.push_str(name);
+        uri.push('=');
+        uri.push_str(value);
+    }
+    uri
+}
+
+/// Returns the path of the file that was opened as `fd`.
+///
+/// Fails if the file is no longer reachable under that path.
+fn attachment_path(fd: OwnedFd) -> io::Result<PathBuf> {
+    let path = fs::read_link(format!("/proc/self/fd/{}", fd.as_raw_fd()))?;
+    let expected = File::from(fd).metadata()?;
+    let actual = fs::metadata(&path)?;
+    if (actual.dev(), actual.ino()) != (expected.dev(), expected.ino()) || !actual.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} does not refer to the attached file", path.display()),
+        ));
+    }
+    Ok(path)
+}
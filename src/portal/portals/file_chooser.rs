@@ -1,41 +1,46 @@
 use {
     crate::{
+        config::{app_rules::AppPolicy, ConfigHandle, LockdownConfig},
         gui::{
             file_chooser,
             file_chooser::{
-                ChoiceVariant, FileChooserError, FileChooserUi, Filter, FilterKind, FinalChoice,
+                ChoiceVariant, FileChooserBackend, FileChooserError, FileChooserUi, Filter,
+                FilterKind, FinalChoice,
             },
-            UiProxy,
         },
         portal::{request::run_request, response::Response},
+        utils::cancellation::Cancellation,
     },
     bstr::{ByteSlice, ByteVec},
     error_reporter::Report,
     serde::Deserializer,
-    std::{ffi::CString, path::Path, str::FromStr},
+    std::{ffi::CString, path::Path, str::FromStr, sync::Arc},
     thiserror::Error,
     url::Url,
     zbus::{
         export::serde::Deserialize,
         interface,
         zvariant::{DeserializeDict, OwnedObjectPath, SerializeDict, Type},
-        ObjectServer,
+        Connection,
     },
 };
 
 pub struct FileChooser {
-    proxy: UiProxy,
+    backend: Arc<dyn FileChooserBackend>,
+    config: ConfigHandle,
 }
 
 impl FileChooser {
-    pub fn new(proxy: &UiProxy) -> Self {
+    pub fn new(backend: Arc<dyn FileChooserBackend>, config: &ConfigHandle) -> Self {
         Self {
-            proxy: proxy.clone(),
+            backend,
+            config: config.clone(),
         }
     }
 }
 
-type Choice = (String, String, Vec<(String, String)>, String);
+/// A choice as passed over D-Bus: id, label, variants and default variant.
+pub type Choice = (String, String, Vec<(String, String)>, String);
 
 type FileFilter = (String, Vec<(u32, String)>);
 
@@ -79,6 +84,7 @@ struct SaveFileOptions {
     choices: Option<Vec<Choice>>,
     current_name: Option<String>,
     current_folder: Option<FilePath>,
+    #[zvariant(rename = "current_file")]
     current_filename: Option<FilePath>,
 }
 
@@ -118,6 +124,8 @@ struct SaveFilesResults {
 
 #[derive(Debug, Error)]
 enum SaveFilesError {
+    #[error(transparent)]
+    SaveToDiskDisabled(#[from] SaveToDiskDisabled),
     #[error("User did not select exactly one path")]
     NotExactlyOnePath,
     #[error("Client tried to save an absolute path")]
@@ -136,6 +144,14 @@ enum SaveFilesError {
     Ui(FileChooserError),
 }
 
+#[derive(Debug, Error)]
+#[error("None of the filters is allowed by the app rules")]
+struct NoFilterAllowed;
+
+#[derive(Debug, Error)]
+#[error("Saving to disk is disabled")]
+struct SaveToDiskDisabled;
+
 impl FileChooser {
     async fn open_file_impl(
         &self,
@@ -143,25 +159,39 @@ impl FileChooser {
         parent_window: String,
         title: String,
         options: OpenFileOptions,
+        cancellation: Cancellation,
     ) -> Response<OpenFileResults> {
-        let res = FileChooserUi {
+        let config = self.config.get();
+        let policy = config.apps.policy(&app_id);
+        let filters = match map_filters(options.filters, &policy) {
+            Ok(filters) => filters,
+            Err(e) => {
+                log::error!("OpenFile failed: {}", Report::new(e));
+                return Response::failed();
+            }
+        };
+        let ui = FileChooserUi {
             title,
             multiple: options.multiple.unwrap_or(false),
             accept_label: options.accept_label,
-            modal: options.modal.unwrap_or(true),
+            modal: options.modal.unwrap_or(config.modal),
             directory: options.directory.unwrap_or(false),
-            filters: options.filters.map(map_filters),
-            current_filter: options.current_filter.map(map_filter),
+            filters,
+            current_filter: map_current_filter(options.current_filter, &policy),
             current_name: None,
-            current_folder: options.current_folder.map(map_cstr),
+            current_folder: policy
+                .current_folder
+                .clone()
+                .or(options.current_folder.map(map_cstr)),
             current_filename: None,
             choices: options.choices.map(map_choices),
             save: false,
             parent_window,
             app_id,
-        }
-        .run(&self.proxy)
-        .await;
+            config,
+            policy,
+        };
+        let res = self.backend.run(ui, cancellation).await;
         match res {
             Ok(res) => Response::success(OpenFileResults {
                 uris: Some(res.uris),
@@ -170,8 +200,9 @@ impl FileChooser {
                 writable: Some(res.writeable),
             }),
             Err(e) => {
+                let response = ui_error_response(&e);
                 log::error!("OpenFile failed: {}", Report::new(e));
-                Response::cancelled()
+                response
             }
         }
     }
@@ -182,25 +213,54 @@ impl FileChooser {
         parent_window: String,
         title: String,
         options: SaveFileOptions,
+        cancellation: Cancellation,
     ) -> Response<SaveFileResults> {
-        let res = FileChooserUi {
+        let config = self.config.get();
+        if let Err(e) = check_save_to_disk(&config.lockdown) {
+            log::error!("SaveFile failed: {}", Report::new(e));
+            return Response::failed();
+        }
+        let policy = config.apps.policy(&app_id);
+        let mut current_name = options.current_name;
+        let mut current_folder = options.current_folder.map(map_cstr);
+        let mut current_filename = options.current_filename.map(map_cstr);
+        if let Some(folder) = &policy.current_folder {
+            // the current filename would override the folder, keep only its name
+            if let Some(filename) = current_filename.take() {
+                current_name = current_name.or_else(|| {
+                    Path::new(&filename)
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                });
+            }
+            current_folder = Some(folder.clone());
+        }
+        let filters = match map_filters(options.filters, &policy) {
+            Ok(filters) => filters,
+            Err(e) => {
+                log::error!("SaveFile failed: {}", Report::new(e));
+                return Response::failed();
+            }
+        };
+        let ui = FileChooserUi {
             title,
             multiple: options.multiple.unwrap_or(false),
             accept_label: options.accept_label,
-            modal: options.modal.unwrap_or(true),
+            modal: options.modal.unwrap_or(config.modal),
             directory: false,
-            filters: options.filters.map(map_filters),
-            current_filter: options.current_filter.map(map_filter),
-            current_name: options.current_name,
-            current_folder: options.current_folder.map(map_cstr),
-            current_filename: options.current_filename.map(map_cstr),
+            filters,
+            current_filter: map_current_filter(options.current_filter, &policy),
+            current_name,
+            current_folder,
+            current_filename,
             choices: options.choices.map(map_choices),
             save: true,
             parent_window,
             app_id,
-        }
-        .run(&self.proxy)
-        .await;
+            config,
+            policy,
+        };
+        let res = self.backend.run(ui, cancellation).await;
         match res {
             Ok(res) => Response::success(SaveFileResults {
                 uris: Some(res.uris),
@@ -208,8 +268,9 @@ impl FileChooser {
                 current_filter: res.current_filter.map(unmap_filter),
             }),
             Err(e) => {
+                let response = ui_error_response(&e);
                 log::error!("SaveFile failed: {}", Report::new(e));
-                Response::cancelled()
+                response
             }
         }
     }
@@ -220,7 +281,10 @@ impl FileChooser {
         parent_window: String,
         title: String,
         options: SaveFilesOptions,
+        cancellation: Cancellation,
     ) -> Result<SaveFilesResults, SaveFilesError> {
+        let config = self.config.get();
+        check_save_to_disk(&config.lockdown)?;
         for file in &options.files {
             let file = Path::new(&file.0);
             // none of the following can be used securely with the current UI
@@ -234,25 +298,33 @@ impl FileChooser {
                 return Err(SaveFilesError::SpecialPath);
             }
         }
-        let mut res = FileChooserUi {
+        let policy = config.apps.policy(&app_id);
+        let ui = FileChooserUi {
             title,
             multiple: false,
             accept_label: options.accept_label,
-            modal: options.modal.unwrap_or(true),
+            modal: options.modal.unwrap_or(config.modal),
             directory: true,
             filters: None,
             current_filter: None,
             current_name: None,
-            current_folder: options.current_folder.map(map_cstr),
+            current_folder: policy
+                .current_folder
+                .clone()
+                .or(options.current_folder.map(map_cstr)),
             current_filename: None,
             choices: options.choices.map(map_choices),
             save: true,
             parent_window,
             app_id,
-        }
-        .run(&self.proxy)
-        .await
-        .map_err(SaveFilesError::Ui)?;
+            config: config.clone(),
+            policy,
+        };
+        let mut res = self
+            .backend
+            .run(ui, cancellation)
+            .await
+            .map_err(SaveFilesError::Ui)?;
         if res.uris.len() != 1 {
             return Err(SaveFilesError::NotExactlyOnePath);
         }
@@ -264,12 +336,12 @@ impl FileChooser {
         for file in &options.files {
             let mut path = base.join(&file.0);
             if path.exists() {
-                let (prefix, dot, suffix) = match file.0.split_once('.') {
-                    Some((prefix, suffix)) => (prefix, ".", suffix),
-                    _ => (file.0.as_str(), "", ""),
+                let (name, ext) = match file.0.find('.') {
+                    Some(idx) => file.0.split_at(idx),
+                    _ => (file.0.as_str(), ""),
                 };
                 for i in 1u64.. {
-                    path = base.join(format!("{prefix} ({i}){dot}{suffix}"));
+                    path = base.join(config.file_chooser.conflict_name(name, i, ext));
                     if !path.exists() {
                         break;
                     }
@@ -293,15 +365,20 @@ impl FileChooser {
         parent_window: String,
         title: String,
         options: SaveFilesOptions,
+        cancellation: Cancellation,
     ) -> Response<SaveFilesResults> {
         match self
-            .try_save_files_impl(app_id, parent_window, title, options)
+            .try_save_files_impl(app_id, parent_window, title, options, cancellation)
             .await
         {
             Ok(res) => Response::success(res),
             Err(e) => {
+                let response = match &e {
+                    SaveFilesError::Ui(e) => ui_error_response(e),
+                    _ => Response::failed(),
+                };
                 log::error!("SaveFiles failed: {}", Report::new(e));
-                Response::cancelled()
+                response
             }
         }
     }
@@ -316,13 +393,11 @@ impl FileChooser {
         parent_window: String,
         title: String,
         options: OpenFileOptions,
-        #[zbus(object_server)] server: &ObjectServer,
+        #[zbus(connection)] conn: &Connection,
     ) -> Response<OpenFileResults> {
-        run_request(
-            server,
-            handle,
-            self.open_file_impl(app_id, parent_window, title, options),
-        )
+        run_request(conn, handle, |cancellation| {
+            self.open_file_impl(app_id, parent_window, title, options, cancellation)
+        })
         .await
     }
 
@@ -333,13 +408,11 @@ impl FileChooser {
         parent_window: String,
         title: String,
         options: SaveFileOptions,
-        #[zbus(object_server)] server: &ObjectServer,
+        #[zbus(connection)] conn: &Connection,
     ) -> Response<SaveFileResults> {
-        run_request(
-            server,
-            handle,
-            self.save_file_impl(app_id, parent_window, title, options),
-        )
+        run_request(conn, handle, |cancellation| {
+            self.save_file_impl(app_id, parent_window, title, options, cancellation)
+        })
         .await
     }
 
@@ -350,19 +423,58 @@ impl FileChooser {
         parent_window: String,
         title: String,
         options: SaveFilesOptions,
-        #[zbus(object_server)] server: &ObjectServer,
+        #[zbus(connection)] conn: &Connection,
     ) -> Response<SaveFilesResults> {
-        run_request(
-            server,
-            handle,
-            self.save_files_impl(app_id, parent_window, title, options),
-        )
+        run_request(conn, handle, |cancellation| {
+            self.save_files_impl(app_id, parent_window, title, options, cancellation)
+        })
         .await
     }
 }
 
-fn map_filters(f: Vec<FileFilter>) -> Vec<Filter> {
-    f.into_iter().map(map_filter).collect()
+/// Maps user cancellations to [`Response::cancelled`] and everything else to
+/// [`Response::failed`].
+fn ui_error_response<T: Type + Default>(e: &FileChooserError) -> Response<T> {
+    match e {
+        FileChooserError::Rejected | FileChooserError::Cancelled => Response::cancelled(),
+        FileChooserError::Closed => Response::failed(),
+    }
+}
+
+fn check_save_to_disk(lockdown: &LockdownConfig) -> Result<(), SaveToDiskDisabled> {
+    if lockdown.disable_save_to_disk {
+        return Err(SaveToDiskDisabled);
+    }
+    Ok(())
+}
+
+/// Returns the filters that the app rules allow.
+///
+/// An unfiltered dialog would offer all files, so this fails if the rules restrict the
+/// filters and none of them remains.
+fn map_filters(
+    f: Option<Vec<FileFilter>>,
+    policy: &AppPolicy,
+) -> Result<Option<Vec<Filter>>, NoFilterAllowed> {
+    let Some(f) = f else {
+        return match policy.filters {
+            Some(_) => Err(NoFilterAllowed),
+            None => Ok(None),
+        };
+    };
+    let filters: Vec<_> = f
+        .into_iter()
+        .filter(|f| policy.allows_filter(&f.0))
+        .map(map_filter)
+        .collect();
+    if filters.is_empty() && policy.filters.is_some() {
+        return Err(NoFilterAllowed);
+    }
+    Ok(Some(filters))
+}
+
+fn map_current_filter(f: Option<FileFilter>, policy: &AppPolicy) -> Option<Filter> {
+    f.filter(|f| policy.allows_filter(&f.0)).map(map_filter)
 }
 
 fn map_filter(f: FileFilter) -> Filter {
@@ -397,7 +509,7 @@ fn map_cstr(f: FilePath) -> String {
     f.0.as_bytes().to_str_lossy().into_owned()
 }
 
-fn map_choices(c: Vec<Choice>) -> Vec<file_chooser::Choice> {
+pub fn map_choices(c: Vec<Choice>) -> Vec<file_chooser::Choice> {
     c.into_iter().map(map_choice).collect()
 }
 
@@ -417,7 +529,7 @@ fn map_choice(c: Choice) -> file_chooser::Choice {
     }
 }
 
-fn map_final_choices(c: Vec<FinalChoice>) -> Vec<(String, String)> {
+pub fn map_final_choices(c: Vec<FinalChoice>) -> Vec<(String, String)> {
     c.into_iter().map(map_final_choice).collect()
 }
 
@@ -0,0 +1,446 @@
+use {
+    crate::{
+        portal::{response::Response, session::Sessions},
+        utils::cancellation::Cancellation,
+    },
+    error_reporter::Report,
+    futures_util::{
+        stream::{self, BoxStream},
+        StreamExt,
+    },
+    std::{pin::pin, sync::Mutex},
+    zbus::{
+        interface,
+        message::Header,
+        names::UniqueName,
+        object_server::SignalContext,
+        zvariant::{DeserializeDict, ObjectPath, OwnedFd, OwnedObjectPath, SerializeDict, Type},
+        CacheProperties, Connection, ObjectServer, Proxy, ProxyBuilder,
+    },
+};
+
+const FLAG_LOGOUT: u32 = 1;
+const FLAG_USER_SWITCH: u32 = 2;
+const FLAG_SUSPEND: u32 = 4;
+const FLAG_IDLE: u32 = 8;
+
+const SESSION_STATE_RUNNING: u32 = 1;
+const SESSION_STATE_QUERY_END: u32 = 2;
+
+const LOGIND_NAME: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+const LOGIND_INTERFACE: &str = "org.freedesktop.login1.Manager";
+
+const SCREENSAVER_NAME: &str = "org.freedesktop.ScreenSaver";
+const SCREENSAVER_PATH: &str = "/org/freedesktop/ScreenSaver";
+const SCREENSAVER_INTERFACE: &str = "org.freedesktop.ScreenSaver";
+
+/// Forwards inhibitions to logind and the screen saver.
+#[derive(Default)]
+pub struct Inhibit {
+    system: Mutex<Option<Connection>>,
+    /// The shutdown delay locks of the monitors.
+    ///
+    /// A lock is released once the app has responded to the end of the session.
+    monitors: Sessions<Option<OwnedFd>>,
+}
+
+impl Inhibit {
+    /// Creates the interface with `system` in place of the system bus.
+    #[cfg(test)]
+    pub fn with_system_bus(system: Connection) -> Self {
+        Self {
+            system: Mutex::new(Some(system)),
+            monitors: Default::default(),
+        }
+    }
+
+    async fn system_bus(&self) -> zbus::Result<Connection> {
+        if let Some(conn) = &*self.system.lock().unwrap() {
+            return Ok(conn.clone());
+        }
+        let conn = Connection::system().await?;
+        *self.system.lock().unwrap() = Some(conn.clone());
+        Ok(conn)
+    }
+}
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct InhibitOptions {
+    reason: Option<String>,
+}
+
+#[derive(SerializeDict, Type, Debug)]
+#[zvariant(signature = "dict")]
+struct State {
+    #[zvariant(rename = "screensaver-active")]
+    screensaver_active: bool,
+    #[zvariant(rename = "session-state")]
+    session_state: u32,
+}
+
+/// Returns the logind inhibitor types of the portal flags.
+fn logind_what(flags: u32) -> String {
+    let mut what = vec![];
+    if flags & FLAG_LOGOUT != 0 {
+        what.push("shutdown");
+    }
+    if flags & FLAG_SUSPEND != 0 {
+        what.push("sleep");
+    }
+    if flags & FLAG_IDLE != 0 {
+        what.push("idle");
+    }
+    what.join(":")
+}
+
+async fn logind_inhibit(
+    system: &Connection,
+    what: &str,
+    who: &str,
+    why: &str,
+    mode: &str,
+) -> zbus::Result<OwnedFd> {
+    let reply = system
+        .call_method(
+            Some(LOGIND_NAME),
+            LOGIND_PATH,
+            Some(LOGIND_INTERFACE),
+            "Inhibit",
+            &(what, who, why, mode),
+        )
+        .await?;
+    reply.body().deserialize()
+}
+
+async fn uninhibit_screensaver(conn: &Connection, cookie: u32) {
+    let res = conn
+        .call_method(
+            Some(SCREENSAVER_NAME),
+            SCREENSAVER_PATH,
+            Some(SCREENSAVER_INTERFACE),
+            "UnInhibit",
+            &(cookie,),
+        )
+        .await;
+    if let Err(e) = res {
+        log::error!("Could not uninhibit the screen saver: {}", Report::new(e));
+    }
+}
+
+async fn proxy(
+    conn: &Connection,
+    name: &'static str,
+    path: &'static str,
+    interface: &'static str,
+) -> zbus::Result<Proxy<'static>> {
+    ProxyBuilder::<Proxy>::new(conn)
+        .destination(name)?
+        .path(path)?
+        .interface(interface)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await
+}
+
+impl Inhibit {
+    async fn inhibit_impl(
+        &self,
+        conn: &Connection,
+        app_id: &str,
+        flags: u32,
+        options: InhibitOptions,
+    ) -> Inhibition {
+        let reason = options.reason.unwrap_or_default();
+        let mut inhibition = Inhibition {
+            conn: conn.clone(),
+            fd: None,
+            cookie: None,
+        };
+        if flags & FLAG_USER_SWITCH != 0 {
+            log::debug!("Ignoring the user switch inhibitor of {app_id}");
+        }
+        let what = logind_what(flags);
+        if !what.is_empty() {
+            let fd = match self.system_bus().await {
+                Ok(system) => logind_inhibit(&system, &what, app_id, &reason, "block").await,
+                Err(e) => Err(e),
+            };
+            match fd {
+                Ok(fd) => inhibition.fd = Some(fd),
+                Err(e) => log::error!("Could not inhibit {what}: {}", Report::new(e)),
+            }
+        }
+        if flags & FLAG_IDLE != 0 {
+            let reply = conn
+                .call_method(
+                    Some(SCREENSAVER_NAME),
+                    SCREENSAVER_PATH,
+                    Some(SCREENSAVER_INTERFACE),
+                    "Inhibit",
+                    &(app_id, reason.as_str()),
+                )
+                .await;
+            match reply.and_then(|r| r.body().deserialize()) {
+                Ok(cookie) => inhibition.cookie = Some(cookie),
+                Err(e) => log::debug!("Could not inhibit the screen saver: {}", Report::new(e)),
+            }
+        }
+        inhibition
+    }
+
+    async fn create_monitor_impl(
+        &self,
+        conn: &Connection,
+        session_handle: OwnedObjectPath,
+        peer: Option<UniqueName<'_>>,
+        app_id: &str,
+    ) -> zbus::Result<()> {
+        let system = match self.system_bus().await {
+            Ok(system) => Some(system),
+            Err(e) => {
+                log::error!("Could not connect to the system bus: {}", Report::new(e));
+                None
+            }
+        };
+        let delay = match &system {
+            Some(system) => delay_shutdown(system, app_id).await,
+            None => None,
+        };
+        let cancellation = self
+            .monitors
+            .create(conn, session_handle.clone(), peer, delay)
+            .await?;
+        let monitor = Monitor {
+            conn: conn.clone(),
+            system,
+            session_handle,
+            app_id: app_id.to_string(),
+            monitors: self.monitors.clone(),
+        };
+        conn.executor()
+            .spawn(monitor.run(cancellation), "inhibit monitor")
+            .detach();
+        Ok(())
+    }
+}
+
+/// Takes a lock that delays shutdown until the app has responded.
+async fn delay_shutdown(system: &Connection, app_id: &str) -> Option<OwnedFd> {
+    let why = "The application may need to save its state";
+    match logind_inhibit(system, "shutdown", app_id, why, "delay").await {
+        Ok(fd) => Some(fd),
+        Err(e) => {
+            log::error!("Could not delay shutdown: {}", Report::new(e));
+            None
+        }
+    }
+}
+
+enum Event {
+    ScreenSaverActive(bool),
+    PrepareForShutdown(bool),
+    Closed,
+}
+
+/// Emits `StateChanged` for one monitor session.
+struct Monitor {
+    conn: Connection,
+    system: Option<Connection>,
+    session_handle: OwnedObjectPath,
+    app_id: String,
+    monitors: Sessions<Option<OwnedFd>>,
+}
+
+impl Monitor {
+    async fn run(self, cancellation: Cancellation) {
+        let mut state = State {
+            screensaver_active: false,
+            session_state: SESSION_STATE_RUNNING,
+        };
+        let screensaver = match self.screensaver().await {
+            Ok((active, events)) => {
+                state.screensaver_active = active;
+                events
+            }
+            Err(e) => {
+                log::debug!("Could not monitor the screen saver: {}", Report::new(e));
+                stream::empty().boxed()
+            }
+        };
+        let shutdown = match self.shutdown().await {
+            Ok(events) => events,
+            Err(e) => {
+                log::error!("Could not monitor shutdown: {}", Report::new(e));
+                stream::empty().boxed()
+            }
+        };
+        let closed =
+            stream::once(async move { cancellation.cancelled().await }).map(|_| Event::Closed);
+        let mut events = pin!(stream::select(
+            closed,
+            stream::select(screensaver, shutdown)
+        ));
+        let ctxt = SignalContext::new(&self.conn, crate::portal::PATH).unwrap();
+        loop {
+            if let Err(e) = Inhibit::state_changed(&ctxt, &self.session_handle, &state).await {
+                log::error!("Could not emit StateChanged: {}", Report::new(e));
+            }
+            match events.next().await {
+                Some(Event::ScreenSaverActive(active)) => state.screensaver_active = active,
+                Some(Event::PrepareForShutdown(true)) => {
+                    state.session_state = SESSION_STATE_QUERY_END;
+                }
+                Some(Event::PrepareForShutdown(false)) => {
+                    state.session_state = SESSION_STATE_RUNNING;
+                    if let Some(system) = &self.system {
+                        let delay = delay_shutdown(system, &self.app_id).await;
+                        self.monitors
+                            .with(&self.session_handle, |lock| *lock = delay);
+                    }
+                }
+                Some(Event::Closed) | None => break,
+            }
+        }
+    }
+
+    async fn screensaver(&self) -> zbus::Result<(bool, BoxStream<'static, Event>)> {
+        let proxy = proxy(
+            &self.conn,
+            SCREENSAVER_NAME,
+            SCREENSAVER_PATH,
+            SCREENSAVER_INTERFACE,
+        )
+        .await?;
+        let signals = proxy.receive_signal("ActiveChanged").await?;
+        let active = proxy.call("GetActive", &()).await?;
+        let events = signals.filter_map(|msg| async move {
+            let active = msg.body().deserialize().ok()?;
+            Some(Event::ScreenSaverActive(active))
+        });
+        Ok((active, events.boxed()))
+    }
+
+    async fn shutdown(&self) -> zbus::Result<BoxStream<'static, Event>> {
+        let Some(system) = &self.system else {
+            return Ok(stream::empty().boxed());
+        };
+        let proxy = proxy(system, LOGIND_NAME, LOGIND_PATH, LOGIND_INTERFACE).await?;
+        let signals = proxy.receive_signal("PrepareForShutdown").await?;
+        let events = signals.filter_map(|msg| async move {
+            let start = msg.body().deserialize().ok()?;
+            Some(Event::PrepareForShutdown(start))
+        });
+        Ok(events.boxed())
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Inhibit")]
+impl Inhibit {
+    #[allow(clippy::too_many_arguments)]
+    async fn inhibit(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(object_server)] server: &ObjectServer,
+        handle: OwnedObjectPath,
+        app_id: String,
+        _window: String,
+        flags: u32,
+        options: InhibitOptions,
+    ) -> zbus::fdo::Result<()> {
+        let inhibition = self.inhibit_impl(conn, &app_id, flags, options).await;
+        match server.at(&handle, inhibition).await {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                log::error!("Request object {} already exists", handle.as_str());
+                Err(zbus::fdo::Error::Failed(
+                    "The inhibit request already exists".to_string(),
+                ))
+            }
+            Err(e) => {
+                log::error!("Could not export the inhibit request: {}", Report::new(&e));
+                Err(zbus::fdo::Error::Failed(
+                    "Could not export the inhibit request".to_string(),
+                ))
+            }
+        }
+    }
+
+    async fn create_monitor(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        _handle: OwnedObjectPath,
+        session_handle: OwnedObjectPath,
+        app_id: String,
+        _window: String,
+    ) -> u32 {
+        let peer = header.sender().cloned();
+        match self
+            .create_monitor_impl(conn, session_handle, peer, &app_id)
+            .await
+        {
+            Ok(()) => Response::success(()).code(),
+            Err(e) => {
+                log::error!("Could not create the monitor: {}", Report::new(e));
+                Response::<()>::failed().code()
+            }
+        }
+    }
+
+    async fn query_end_response(&self, session_handle: OwnedObjectPath) {
+        self.monitors.with(&session_handle, |lock| *lock = None);
+    }
+
+    #[zbus(signal)]
+    async fn state_changed(
+        ctxt: &SignalContext<'_>,
+        session_handle: &ObjectPath<'_>,
+        state: &State,
+    ) -> zbus::Result<()>;
+}
+
+/// An active inhibition. It is released once the request is closed.
+struct Inhibition {
+    conn: Connection,
+    /// The logind inhibitor lock.
+    fd: Option<OwnedFd>,
+    /// The cookie of the screen saver inhibition.
+    cookie: Option<u32>,
+}
+
+/// If the inhibition is dropped without having been closed, e.g. because it could not be
+/// exported, the screen saver inhibition is released in the background.
+impl Drop for Inhibition {
+    fn drop(&mut self) {
+        let Some(cookie) = self.cookie.take() else {
+            return;
+        };
+        let conn = self.conn.clone();
+        self.conn
+            .executor()
+            .spawn(
+                async move { uninhibit_screensaver(&conn, cookie).await },
+                "uninhibit",
+            )
+            .detach();
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Request")]
+impl Inhibition {
+    async fn close(
+        &mut self,
+        #[zbus(object_server)] server: &ObjectServer,
+        #[zbus(header)] header: Header<'_>,
+    ) {
+        self.fd = None;
+        if let Some(cookie) = self.cookie.take() {
+            uninhibit_screensaver(&self.conn, cookie).await;
+        }
+        if let Some(path) = header.path() {
+            let _ = server.remove::<Self, ObjectPath>(path.as_ref()).await;
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use {
+    crate::{config::ConfigHandle, portal::PATH},
+    error_reporter::Report,
+    zbus::{blocking::Connection, interface},
+};
+
+/// Publishes the lockdown settings of the configuration file.
+pub struct Lockdown {
+    config: ConfigHandle,
+}
+
+/// Adds the interface and emits `PropertiesChanged` whenever the configuration is
+/// reloaded with different values.
+pub fn serve(connection: &Connection, config: &ConfigHandle) -> zbus::Result<()> {
+    let lockdown = Lockdown {
+        config: config.clone(),
+    };
+    connection.object_server().at(PATH, lockdown)?;
+    let conn = connection.inner().clone();
+    let updates = config.watch();
+    let mut old = config.get().lockdown.clone();
+    let task = async move {
+        let iface = match conn.object_server().interface::<_, Lockdown>(PATH).await {
+            Ok(iface) => iface,
+            Err(e) => {
+                log::error!(
+                    "Could not access the lockdown interface: {}",
+                    Report::new(e)
+                );
+                return;
+            }
+        };
+        while let Ok(config) = updates.recv().await {
+            let new = config.lockdown.clone();
+            let lockdown = iface.get().await;
+            let ctxt = iface.signal_context();
+            macro_rules! emit {
+                ($($field:ident: $signal:ident,)*) => {
+                    $(
+                        if old.$field != new.$field {
+                            if let Err(e) = lockdown.$signal(ctxt).await {
+                                log::error!("Could not emit PropertiesChanged: {}", Report::new(e));
+                            }
+                        }
+                    )*
+                };
+            }
+            emit! {
+                disable_printing: disable_printing_changed,
+                disable_save_to_disk: disable_save_to_disk_changed,
+                disable_application_handlers: disable_application_handlers_changed,
+                disable_location: disable_location_changed,
+                disable_camera: disable_camera_changed,
+                disable_microphone: disable_microphone_changed,
+                disable_sound_output: disable_sound_output_changed,
+            }
+            old = new;
+        }
+    };
+    connection
+        .inner()
+        .executor()
+        .spawn(task, "lockdown updates")
+        .detach();
+    Ok(())
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Lockdown")]
+impl Lockdown {
+    #[zbus(property, name = "disable-printing")]
+    fn disable_printing(&self) -> bool {
+        self.config.get().lockdown.disable_printing
+    }
+
+    #[zbus(property, name = "disable-save-to-disk")]
+    fn disable_save_to_disk(&self) -> bool {
+        self.config.get().lockdown.disable_save_to_disk
+    }
+
+    #[zbus(property, name = "disable-application-handlers")]
+    fn disable_application_handlers(&self) -> bool {
+        self.config.get().lockdown.disable_application_handlers
+    }
+
+    #[zbus(property, name = "disable-location")]
+    fn disable_location(&self) -> bool {
+        self.config.get().lockdown.disable_location
+    }
+
+    #[zbus(property, name = "disable-camera")]
+    fn disable_camera(&self) -> bool {
+        self.config.get().lockdown.disable_camera
+    }
+
+    #[zbus(property, name = "disable-microphone")]
+    fn disable_microphone(&self) -> bool {
+        self.config.get().lockdown.disable_microphone
+    }
+
+    #[zbus(property, name = "disable-sound-output")]
+    fn disable_sound_output(&self) -> bool {
+        self.config.get().lockdown.disable_sound_output
+    }
+}
@@ -0,0 +1,382 @@
+use {
+    crate::{portal::PATH, utils::icon::Icon},
+    async_channel::Receiver,
+    error_reporter::Report,
+    futures_util::StreamExt,
+    gtk4::{
+        gdk_pixbuf::Pixbuf,
+        gio::{Cancellable, MemoryInputStream},
+        glib,
+    },
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    },
+    zbus::{
+        blocking::Connection,
+        interface,
+        object_server::SignalContext,
+        zvariant::{OwnedValue, Structure, Value},
+        CacheProperties, Proxy, ProxyBuilder,
+    },
+};
+
+const SERVER_NAME: &str = "org.freedesktop.Notifications";
+const SERVER_PATH: &str = "/org/freedesktop/Notifications";
+const SERVER_INTERFACE: &str = "org.freedesktop.Notifications";
+
+/// The action key of the default action.
+const DEFAULT_ACTION: &str = "default";
+
+/// Forwards notifications to the notification server on the session bus.
+pub struct Notification {
+    conn: zbus::Connection,
+    state: Arc<Mutex<State>>,
+    /// Closed once the portal listens to the signals of the notification server.
+    ready: Receiver<()>,
+}
+
+#[derive(Default)]
+struct State {
+    active: HashMap<Key, Active>,
+    keys: HashMap<u32, Key>,
+}
+
+/// The app id and the id of a notification.
+type Key = (String, String);
+
+struct Active {
+    server_id: u32,
+    /// The portal actions by action key.
+    actions: HashMap<String, Action>,
+}
+
+struct Action {
+    name: String,
+    target: Option<OwnedValue>,
+}
+
+/// A notification as passed by the client.
+///
+/// Targets can have any type, so the notification is parsed manually.
+#[derive(Debug, Default)]
+struct PortalNotification {
+    title: Option<String>,
+    body: Option<String>,
+    icon: Option<OwnedValue>,
+    priority: Option<String>,
+    default_action: Option<String>,
+    default_action_target: Option<OwnedValue>,
+    buttons: Vec<Button>,
+}
+
+#[derive(Debug, Default)]
+struct Button {
+    label: Option<String>,
+    action: Option<String>,
+    target: Option<OwnedValue>,
+}
+
+type Dict = HashMap<String, OwnedValue>;
+
+impl PortalNotification {
+    /// Parses a notification. Values of the wrong type are ignored.
+    fn parse(mut dict: Dict) -> Self {
+        let buttons = dict
+            .remove("buttons")
+            .and_then(|b| Vec::<Dict>::try_from(b).ok())
+            .unwrap_or_default();
+        Self {
+            title: string(&mut dict, "title"),
+            body: string(&mut dict, "body"),
+            icon: dict.remove("icon"),
+            priority: string(&mut dict, "priority"),
+            default_action: string(&mut dict, "default-action"),
+            default_action_target: dict.remove("default-action-target"),
+            buttons: buttons
+                .into_iter()
+                .map(|mut b| Button {
+                    label: string(&mut b, "label"),
+                    action: string(&mut b, "action"),
+                    target: b.remove("target"),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn string(dict: &mut Dict, key: &str) -> Option<String> {
+    dict.remove(key).and_then(|v| String::try_from(v).ok())
+}
+
+/// Adds the interface and forwards the actions invoked on the notification server.
+pub fn serve(connection: &Connection) -> zbus::Result<()> {
+    let (ready, ready_recv) = async_channel::bounded(1);
+    let notification = Notification {
+        conn: connection.inner().clone(),
+        state: Default::default(),
+        ready: ready_recv,
+    };
+    let state = notification.state.clone();
+    connection.object_server().at(PATH, notification)?;
+    let conn = connection.inner().clone();
+    let task = async move {
+        let signals = async {
+            let proxy = ProxyBuilder::<Proxy>::new(&conn)
+                .destination(SERVER_NAME)?
+                .path(SERVER_PATH)?
+                .interface(SERVER_INTERFACE)?
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await?;
+            proxy.receive_all_signals().await
+        };
+        let mut signals = match signals.await {
+            Ok(s) => s,
+            Err(e) => {
+                log::error!(
+                    "Could not subscribe to the notification server: {}",
+                    Report::new(e)
+                );
+                return;
+            }
+        };
+        ready.close();
+        let ctxt = SignalContext::new(&conn, PATH).unwrap();
+        while let Some(msg) = signals.next().await {
+            let header = msg.header();
+            let body = msg.body();
+            match header.member().map(|m| m.as_str()) {
+                Some("ActionInvoked") => {
+                    let Ok((server_id, key)) = body.deserialize::<(u32, String)>() else {
+                        continue;
+                    };
+                    let invoked = state.lock().unwrap().invoked(server_id, &key);
+                    let Some(((app_id, id), action, parameter)) = invoked else {
+                        continue;
+                    };
+                    let res =
+                        Notification::action_invoked(&ctxt, &app_id, &id, &action, parameter).await;
+                    if let Err(e) = res {
+                        log::error!("Could not emit ActionInvoked: {}", Report::new(e));
+                    }
+                }
+                Some("NotificationClosed") => {
+                    if let Ok((server_id, _)) = body.deserialize::<(u32, u32)>() {
+                        state.lock().unwrap().closed(server_id);
+                    }
+                }
+                _ => {}
+            }
+        }
+    };
+    connection
+        .inner()
+        .executor()
+        .spawn(task, "notification signals")
+        .detach();
+    Ok(())
+}
+
+impl State {
+    /// Returns the notification, action and parameter of an invoked action.
+    fn invoked(&self, server_id: u32, key: &str) -> Option<(Key, String, Vec<OwnedValue>)> {
+        let k = self.keys.get(&server_id)?;
+        let action = self.active.get(k)?.actions.get(key)?;
+        let parameter = action
+            .target
+            .iter()
+            .flat_map(|t| t.try_clone().ok())
+            .collect();
+        Some((k.clone(), action.name.clone(), parameter))
+    }
+
+    fn closed(&mut self, server_id: u32) {
+        if let Some(key) = self.keys.remove(&server_id) {
+            self.active.remove(&key);
+        }
+    }
+}
+
+impl Notification {
+    async fn add_notification_impl(
+        &self,
+        app_id: String,
+        id: String,
+        notification: PortalNotification,
+    ) -> zbus::Result<()> {
+        let _ = self.ready.recv().await;
+        let key = (app_id, id);
+        let replaces_id = self
+            .state
+            .lock()
+            .unwrap()
+            .active
+            .get(&key)
+            .map(|a| a.server_id)
+            .unwrap_or(0);
+        let mut actions = HashMap::new();
+        let mut action_list = vec![];
+        if let Some(name) = notification.default_action {
+            let action = Action {
+                name,
+                target: notification.default_action_target,
+            };
+            actions.insert(DEFAULT_ACTION.to_string(), action);
+            action_list.extend([DEFAULT_ACTION.to_string(), String::new()]);
+        }
+        for (idx, button) in notification.buttons.into_iter().enumerate() {
+            let (Some(label), Some(name)) = (button.label, button.action) else {
+                continue;
+            };
+            let action_key = format!("button-{idx}");
+            let action = Action {
+                name,
+                target: button.target,
+            };
+            actions.insert(action_key.clone(), action);
+            action_list.extend([action_key, label]);
+        }
+        let urgency: u8 = match notification.priority.as_deref() {
+            Some("low") => 0,
+            Some("urgent") => 2,
+            _ => 1,
+        };
+        let mut hints = HashMap::new();
+        hints.insert("urgency", Value::from(urgency));
+        hints.insert("desktop-entry", Value::from(key.0.as_str()));
+        let expire_timeout = match urgency {
+            2 => 0,
+            _ => -1,
+        };
+        let icon = notification.icon.as_ref().and_then(Icon::parse);
+        if let Some(data) = icon.as_ref().and_then(image_data) {
+            hints.insert("image-data", data);
+        }
+        let icon = icon.and_then(icon_name).unwrap_or_default();
+        let body = (
+            key.0.as_str(),
+            replaces_id,
+            icon.as_str(),
+            notification.title.as_deref().unwrap_or_default(),
+            notification.body.as_deref().unwrap_or_default(),
+            &action_list,
+            hints,
+            expire_timeout,
+        );
+        let reply = self
+            .conn
+            .call_method(
+                Some(SERVER_NAME),
+                SERVER_PATH,
+                Some(SERVER_INTERFACE),
+                "Notify",
+                &body,
+            )
+            .await?;
+        let server_id: u32 = reply.body().deserialize()?;
+        let mut state = self.state.lock().unwrap();
+        if replaces_id != 0 {
+            state.keys.remove(&replaces_id);
+        }
+        state.keys.insert(server_id, key.clone());
+        state.active.insert(key, Active { server_id, actions });
+        Ok(())
+    }
+
+    async fn remove_notification_impl(&self, app_id: String, id: String) -> zbus::Result<()> {
+        let active = {
+            let mut state = self.state.lock().unwrap();
+            let active = state.active.remove(&(app_id, id));
+            if let Some(active) = &active {
+                state.keys.remove(&active.server_id);
+            }
+            active
+        };
+        let Some(active) = active else {
+            return Ok(());
+        };
+        self.conn
+            .call_method(
+                Some(SERVER_NAME),
+                SERVER_PATH,
+                Some(SERVER_INTERFACE),
+                "CloseNotification",
+                &(active.server_id,),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Notification")]
+impl Notification {
+    async fn add_notification(
+        &self,
+        app_id: String,
+        id: String,
+        notification: Dict,
+    ) -> zbus::fdo::Result<()> {
+        let notification = PortalNotification::parse(notification);
+        self.add_notification_impl(app_id, id, notification)
+            .await
+            .map_err(|e| {
+                log::error!("Could not show the notification: {}", Report::new(&e));
+                zbus::fdo::Error::Failed("Could not show the notification".to_string())
+            })
+    }
+
+    async fn remove_notification(&self, app_id: String, id: String) -> zbus::fdo::Result<()> {
+        self.remove_notification_impl(app_id, id)
+            .await
+            .map_err(|e| {
+                log::error!("Could not remove the notification: {}", Report::new(&e));
+                zbus::fdo::Error::Failed("Could not remove the notification".to_string())
+            })
+    }
+
+    #[zbus(signal)]
+    async fn action_invoked(
+        ctxt: &SignalContext<'_>,
+        app_id: &str,
+        id: &str,
+        action: &str,
+        parameter: Vec<OwnedValue>,
+    ) -> zbus::Result<()>;
+}
+
+/// Returns the icon name or path of an icon.
+///
+/// Bytes icons are sent as the `image-data` hint instead, see [`image_data`].
+fn icon_name(icon: Icon) -> Option<String> {
+    match icon {
+        Icon::Themed(names) => names.into_iter().next(),
+        Icon::File(path) => Some(path.to_string_lossy().into_owned()),
+        Icon::Bytes(_) => None,
+    }
+}
+
+/// Decodes a bytes icon into the `image-data` hint, a raw image of type `(iiibiiay)`.
+fn image_data(icon: &Icon) -> Option<Value<'static>> {
+    let Icon::Bytes(bytes) = icon else {
+        return None;
+    };
+    let stream = MemoryInputStream::from_bytes(&glib::Bytes::from(bytes));
+    let pixbuf = match Pixbuf::from_stream(&stream, None::<&Cancellable>) {
+        Ok(pixbuf) => pixbuf,
+        Err(e) => {
+            log::warn!("Could not decode the notification icon: {}", Report::new(e));
+            return None;
+        }
+    };
+    let data = (
+        pixbuf.width(),
+        pixbuf.height(),
+        pixbuf.rowstride(),
+        pixbuf.has_alpha(),
+        pixbuf.bits_per_sample(),
+        pixbuf.n_channels(),
+        pixbuf.read_pixel_bytes().to_vec(),
+    );
+    Some(Value::new(Structure::from(data)))
+}
@@ -0,0 +1,323 @@
+use {
+    crate::{
+        config::ConfigHandle,
+        gui::print::{PageSetup, PrintBackend, PrintDocument, PrintError, PrintSetup, PrintUi},
+        portal::{request::run_request, response::Response},
+        utils::cancellation::Cancellation,
+    },
+    error_reporter::Report,
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    },
+    zbus::{
+        interface,
+        zvariant::{self, DeserializeDict, OwnedObjectPath, OwnedValue, SerializeDict, Str, Type},
+        Connection,
+    },
+};
+
+pub struct Print {
+    backend: Arc<dyn PrintBackend>,
+    config: ConfigHandle,
+    prepared: Mutex<HashMap<u32, PreparedPrint>>,
+}
+
+/// The maximum number of unused print tokens. When another token is created, the oldest
+/// one is discarded.
+const MAX_PREPARED: usize = 16;
+
+/// How long a print token remains valid.
+const PREPARED_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// The result of a `PreparePrint` call that has not yet been used by `Print`.
+struct PreparedPrint {
+    app_id: String,
+    setup: PrintSetup,
+    created: Instant,
+}
+
+impl Print {
+    pub fn new(backend: Arc<dyn PrintBackend>, config: &ConfigHandle) -> Self {
+        Self {
+            backend,
+            config: config.clone(),
+            prepared: Default::default(),
+        }
+    }
+}
+
+type Settings = HashMap<String, OwnedValue>;
+
+#[derive(DeserializeDict, SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct PageSetupDict {
+    #[zvariant(rename = "PPDName")]
+    ppd_name: Option<String>,
+    #[zvariant(rename = "Name")]
+    name: Option<String>,
+    #[zvariant(rename = "DisplayName")]
+    display_name: Option<String>,
+    #[zvariant(rename = "Width")]
+    width: Option<f64>,
+    #[zvariant(rename = "Height")]
+    height: Option<f64>,
+    #[zvariant(rename = "MarginTop")]
+    margin_top: Option<f64>,
+    #[zvariant(rename = "MarginBottom")]
+    margin_bottom: Option<f64>,
+    #[zvariant(rename = "MarginLeft")]
+    margin_left: Option<f64>,
+    #[zvariant(rename = "MarginRight")]
+    margin_right: Option<f64>,
+    #[zvariant(rename = "Orientation")]
+    orientation: Option<String>,
+}
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct PreparePrintOptions {
+    modal: Option<bool>,
+    accept_label: Option<String>,
+}
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct PreparePrintResults {
+    settings: Option<Settings>,
+    #[zvariant(rename = "page-setup")]
+    page_setup: Option<PageSetupDict>,
+    token: Option<u32>,
+}
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct PrintOptions {
+    modal: Option<bool>,
+    token: Option<u32>,
+}
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct PrintResults {}
+
+impl Print {
+    async fn prepare_print_impl(
+        &self,
+        app_id: String,
+        parent_window: String,
+        title: String,
+        setup: PrintSetup,
+        options: PreparePrintOptions,
+        cancellation: Cancellation,
+    ) -> Response<PreparePrintResults> {
+        let ui = PrintUi {
+            title,
+            modal: options.modal.unwrap_or(self.config.get().modal),
+            accept_label: options.accept_label,
+            setup,
+            parent_window,
+        };
+        match self.backend.prepare(ui, cancellation).await {
+            Ok(setup) => {
+                let results = PreparePrintResults {
+                    settings: Some(unmap_settings(&setup.settings)),
+                    page_setup: Some(unmap_page_setup(&setup.page_setup)),
+                    token: Some(self.add_prepared(app_id, setup)),
+                };
+                Response::success(results)
+            }
+            Err(e) => {
+                let response = error_response(&e);
+                log::error!("PreparePrint failed: {}", Report::new(e));
+                response
+            }
+        }
+    }
+
+    async fn print_impl(
+        &self,
+        app_id: String,
+        parent_window: String,
+        title: String,
+        fd: zvariant::OwnedFd,
+        options: PrintOptions,
+        cancellation: Cancellation,
+    ) -> Response<PrintResults> {
+        let prepared = options.token.and_then(|t| self.take_prepared(t, &app_id));
+        let setup = match prepared {
+            Some(setup) => Ok(setup),
+            None => {
+                let ui = PrintUi {
+                    title: title.clone(),
+                    modal: options.modal.unwrap_or(self.config.get().modal),
+                    accept_label: None,
+                    setup: PrintSetup::default(),
+                    parent_window,
+                };
+                self.backend.prepare(ui, cancellation).await
+            }
+        };
+        let res = match setup {
+            Ok(setup) => {
+                let document = PrintDocument {
+                    title,
+                    fd: fd.into(),
+                    setup,
+                };
+                self.backend.print(document).await
+            }
+            Err(e) => Err(e),
+        };
+        match res {
+            Ok(()) => Response::success(PrintResults::default()),
+            Err(e) => {
+                let response = error_response(&e);
+                log::error!("Print failed: {}", Report::new(e));
+                response
+            }
+        }
+    }
+
+    /// Stores the setup and returns the token that can be used to print with it.
+    ///
+    /// Expired tokens are discarded and at most [`MAX_PREPARED`] tokens are kept.
+    fn add_prepared(&self, app_id: String, setup: PrintSetup) -> u32 {
+        let mut prepared = self.prepared.lock().unwrap();
+        prepared.retain(|_, p| p.created.elapsed() < PREPARED_LIFETIME);
+        while prepared.len() >= MAX_PREPARED {
+            let oldest = prepared
+                .iter()
+                .min_by_key(|(_, p)| p.created)
+                .map(|(&token, _)| token);
+            prepared.remove(&oldest.unwrap());
+        }
+        let token = loop {
+            let token = rand::random();
+            if token != 0 && !prepared.contains_key(&token) {
+                break token;
+            }
+        };
+        let print = PreparedPrint {
+            app_id,
+            setup,
+            created: Instant::now(),
+        };
+        prepared.insert(token, print);
+        token
+    }
+
+    /// Removes and returns the setup of a token if it was created for the application.
+    fn take_prepared(&self, token: u32, app_id: &str) -> Option<PrintSetup> {
+        let mut prepared = self.prepared.lock().unwrap();
+        let print = prepared.get(&token)?;
+        if print.created.elapsed() >= PREPARED_LIFETIME {
+            log::warn!("Application {app_id} tried to use an expired print token");
+            prepared.remove(&token);
+            return None;
+        }
+        if print.app_id != app_id {
+            log::warn!("Application {app_id} tried to use a print token of another application");
+            return None;
+        }
+        prepared.remove(&token).map(|p| p.setup)
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Print")]
+impl Print {
+    #[allow(clippy::too_many_arguments)]
+    async fn prepare_print(
+        &self,
+        handle: OwnedObjectPath,
+        app_id: String,
+        parent_window: String,
+        title: String,
+        settings: Settings,
+        page_setup: PageSetupDict,
+        options: PreparePrintOptions,
+        #[zbus(connection)] conn: &Connection,
+    ) -> Response<PreparePrintResults> {
+        let setup = PrintSetup {
+            settings: map_settings(settings),
+            page_setup: map_page_setup(page_setup),
+        };
+        run_request(conn, handle, |cancellation| {
+            self.prepare_print_impl(app_id, parent_window, title, setup, options, cancellation)
+        })
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn print(
+        &self,
+        handle: OwnedObjectPath,
+        app_id: String,
+        parent_window: String,
+        title: String,
+        fd: zvariant::OwnedFd,
+        options: PrintOptions,
+        #[zbus(connection)] conn: &Connection,
+    ) -> Response<PrintResults> {
+        run_request(conn, handle, |cancellation| {
+            self.print_impl(app_id, parent_window, title, fd, options, cancellation)
+        })
+        .await
+    }
+}
+
+/// Maps user cancellations to [`Response::cancelled`] and everything else to
+/// [`Response::failed`].
+fn error_response<T: Type + Default>(e: &PrintError) -> Response<T> {
+    match e {
+        PrintError::Rejected | PrintError::Cancelled => Response::cancelled(),
+        _ => Response::failed(),
+    }
+}
+
+/// Keeps the string values of the settings.
+///
+/// `GtkPrintSettings` stores all values as strings.
+fn map_settings(settings: Settings) -> HashMap<String, String> {
+    settings
+        .into_iter()
+        .flat_map(|(k, v)| String::try_from(v).ok().map(|v| (k, v)))
+        .collect()
+}
+
+fn unmap_settings(settings: &HashMap<String, String>) -> Settings {
+    settings
+        .iter()
+        .map(|(k, v)| (k.clone(), Str::from(v.clone()).into()))
+        .collect()
+}
+
+macro_rules! page_setup_fields {
+    ($($field:ident,)*) => {
+        fn map_page_setup(p: PageSetupDict) -> PageSetup {
+            PageSetup {
+                $($field: p.$field,)*
+            }
+        }
+
+        fn unmap_page_setup(p: &PageSetup) -> PageSetupDict {
+            PageSetupDict {
+                $($field: p.$field.clone(),)*
+            }
+        }
+    };
+}
+
+page_setup_fields! {
+    ppd_name,
+    name,
+    display_name,
+    width,
+    height,
+    margin_top,
+    margin_bottom,
+    margin_left,
+    margin_right,
+    orientation,
+}
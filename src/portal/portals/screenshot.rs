@@ -0,0 +1,107 @@
+use {
+    crate::{
+        config::ConfigHandle,
+        gui::{color_picker::ColorUi, DialogBackend, DialogError},
+        portal::{request::run_request, response::Response},
+        utils::cancellation::Cancellation,
+    },
+    error_reporter::Report,
+    std::{collections::HashMap, sync::Arc},
+    zbus::{
+        interface,
+        zvariant::{OwnedObjectPath, OwnedValue, SerializeDict, Type},
+        Connection,
+    },
+};
+
+pub struct Screenshot {
+    backend: Arc<dyn DialogBackend<ColorUi>>,
+    config: ConfigHandle,
+}
+
+impl Screenshot {
+    pub fn new(backend: Arc<dyn DialogBackend<ColorUi>>, config: &ConfigHandle) -> Self {
+        Self {
+            backend,
+            config: config.clone(),
+        }
+    }
+}
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct ScreenshotResults {
+    uri: Option<String>,
+}
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct PickColorResults {
+    color: Option<(f64, f64, f64)>,
+}
+
+impl Screenshot {
+    async fn pick_color_impl(
+        &self,
+        app_id: String,
+        parent_window: String,
+        cancellation: Cancellation,
+    ) -> Response<PickColorResults> {
+        let ui = ColorUi {
+            app_id,
+            modal: self.config.get().modal,
+            parent_window,
+        };
+        match self.backend.run(ui, cancellation).await {
+            Ok(c) => Response::success(PickColorResults {
+                color: Some((c.red, c.green, c.blue)),
+            }),
+            Err(e @ (DialogError::Rejected | DialogError::Cancelled)) => {
+                log::debug!("PickColor was not confirmed: {}", Report::new(e));
+                Response::cancelled()
+            }
+            Err(e) => {
+                log::error!("PickColor failed: {}", Report::new(e));
+                Response::failed()
+            }
+        }
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Screenshot")]
+impl Screenshot {
+    /// Screenshots need compositor support that is not implemented yet.
+    async fn screenshot(
+        &self,
+        handle: OwnedObjectPath,
+        app_id: String,
+        _parent_window: String,
+        _options: HashMap<String, OwnedValue>,
+        #[zbus(connection)] conn: &Connection,
+    ) -> Response<ScreenshotResults> {
+        run_request(conn, handle, |_| async move {
+            log::error!("Cannot take a screenshot for {app_id}: Screen capture is not supported");
+            Response::failed()
+        })
+        .await
+    }
+
+    async fn pick_color(
+        &self,
+        handle: OwnedObjectPath,
+        app_id: String,
+        parent_window: String,
+        _options: HashMap<String, OwnedValue>,
+        #[zbus(connection)] conn: &Connection,
+    ) -> Response<PickColorResults> {
+        run_request(conn, handle, |cancellation| {
+            self.pick_color_impl(app_id, parent_window, cancellation)
+        })
+        .await
+    }
+
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        2
+    }
+}
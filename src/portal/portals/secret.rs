@@ -0,0 +1,265 @@
+use {
+    crate::{
+        portal::{request::run_request, response::Response},
+        utils::cancellation::Cancellation,
+    },
+    error_reporter::Report,
+    futures_util::{select, FutureExt, StreamExt},
+    rand::Rng,
+    std::{
+        collections::HashMap,
+        fs::File,
+        io::{self, Write},
+        os::fd::OwnedFd,
+    },
+    thiserror::Error,
+    zbus::{
+        interface,
+        zvariant::{self, ObjectPath, OwnedObjectPath, OwnedValue, SerializeDict, Type, Value},
+        CacheProperties, Connection, Proxy, ProxyBuilder,
+    },
+};
+
+const SECRETS_NAME: &str = "org.freedesktop.secrets";
+const SECRETS_PATH: &str = "/org/freedesktop/secrets";
+const SERVICE_INTERFACE: &str = "org.freedesktop.Secret.Service";
+const COLLECTION_INTERFACE: &str = "org.freedesktop.Secret.Collection";
+const ITEM_INTERFACE: &str = "org.freedesktop.Secret.Item";
+const SESSION_INTERFACE: &str = "org.freedesktop.Secret.Session";
+const PROMPT_INTERFACE: &str = "org.freedesktop.Secret.Prompt";
+
+/// The number of random bytes in a new secret.
+const SECRET_LEN: usize = 64;
+
+#[derive(Debug, Error)]
+enum SecretError {
+    #[error("The Secret Service failed")]
+    Service(#[from] zbus::Error),
+    #[error("The prompt was dismissed")]
+    Dismissed,
+    #[error("The request was cancelled")]
+    Cancelled,
+    #[error("The Secret Service has no default collection")]
+    NoCollection,
+    #[error("Could not unlock the secret")]
+    Locked,
+    #[error("Could not write the secret")]
+    Write(#[source] io::Error),
+}
+
+/// A secret as transferred by the Secret Service: session, parameters, value and
+/// content type.
+type ServiceSecret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+/// Serves application secrets that are stored in the Secret Service.
+pub struct Secret;
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct RetrieveSecretResults {}
+
+impl Secret {
+    async fn retrieve_secret_impl(
+        &self,
+        conn: &Connection,
+        app_id: String,
+        fd: OwnedFd,
+        cancellation: Cancellation,
+    ) -> Response<RetrieveSecretResults> {
+        let res = async {
+            let secret = retrieve(conn, &app_id, &cancellation).await?;
+            File::from(fd)
+                .write_all(&secret)
+                .map_err(SecretError::Write)
+        };
+        match res.await {
+            Ok(()) => Response::success(RetrieveSecretResults::default()),
+            Err(e @ (SecretError::Dismissed | SecretError::Cancelled)) => {
+                log::debug!("RetrieveSecret was not confirmed: {}", Report::new(e));
+                Response::cancelled()
+            }
+            Err(e) => {
+                log::error!(
+                    "Could not retrieve the secret of {app_id}: {}",
+                    Report::new(e)
+                );
+                Response::failed()
+            }
+        }
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Secret")]
+impl Secret {
+    async fn retrieve_secret(
+        &self,
+        handle: OwnedObjectPath,
+        app_id: String,
+        fd: zvariant::OwnedFd,
+        _options: HashMap<String, OwnedValue>,
+        #[zbus(connection)] conn: &Connection,
+    ) -> Response<RetrieveSecretResults> {
+        run_request(conn, handle, |cancellation| {
+            self.retrieve_secret_impl(conn, app_id, fd.into(), cancellation)
+        })
+        .await
+    }
+
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        1
+    }
+}
+
+/// Returns the secret of `app_id`, creating it if it does not exist yet.
+async fn retrieve(
+    conn: &Connection,
+    app_id: &str,
+    cancellation: &Cancellation,
+) -> Result<Vec<u8>, SecretError> {
+    let service = proxy(conn, SECRETS_PATH, SERVICE_INTERFACE).await?;
+    let (_, session): (OwnedValue, OwnedObjectPath) = service
+        .call("OpenSession", &("plain", Value::new("")))
+        .await?;
+    let res = retrieve_in_session(conn, &service, &session, app_id, cancellation).await;
+    let session = proxy(conn, &session, SESSION_INTERFACE).await?;
+    if let Err(e) = session.call_method("Close", &()).await {
+        log::warn!("Could not close the secret session: {}", Report::new(e));
+    }
+    res
+}
+
+async fn retrieve_in_session(
+    conn: &Connection,
+    service: &Proxy<'_>,
+    session: &ObjectPath<'_>,
+    app_id: &str,
+    cancellation: &Cancellation,
+) -> Result<Vec<u8>, SecretError> {
+    let attributes = HashMap::from([("app_id", app_id)]);
+    let (unlocked, locked): (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>) =
+        service.call("SearchItems", &(&attributes,)).await?;
+    let item = match (unlocked.into_iter().next(), locked.is_empty()) {
+        (Some(item), _) => Some(item),
+        (None, false) => Some(unlock(conn, service, locked, cancellation).await?),
+        (None, true) => None,
+    };
+    if let Some(item) = item {
+        let item = proxy(conn, &item, ITEM_INTERFACE).await?;
+        let ((_, _, secret, _),): (ServiceSecret,) = item.call("GetSecret", &(session,)).await?;
+        return Ok(secret);
+    }
+    let mut secret = vec![0; SECRET_LEN];
+    rand::thread_rng().fill(&mut secret[..]);
+    let collection = default_collection(conn, service, cancellation).await?;
+    let collection = proxy(conn, &collection, COLLECTION_INTERFACE).await?;
+    let label = format!("Application key for {app_id}");
+    let properties = HashMap::from([
+        ("org.freedesktop.Secret.Item.Label", Value::new(label)),
+        (
+            "org.freedesktop.Secret.Item.Attributes",
+            Value::new(attributes),
+        ),
+    ]);
+    let value = (
+        session,
+        Vec::<u8>::new(),
+        &secret,
+        "application/octet-stream",
+    );
+    let (_, prompt_path): (OwnedObjectPath, OwnedObjectPath) = collection
+        .call("CreateItem", &(properties, value, true))
+        .await?;
+    prompt(conn, &prompt_path, cancellation).await?;
+    Ok(secret)
+}
+
+/// Unlocks the items and returns the first of them.
+async fn unlock(
+    conn: &Connection,
+    service: &Proxy<'_>,
+    items: Vec<OwnedObjectPath>,
+    cancellation: &Cancellation,
+) -> Result<OwnedObjectPath, SecretError> {
+    let (unlocked, prompt_path): (Vec<OwnedObjectPath>, OwnedObjectPath) =
+        service.call("Unlock", &(items,)).await?;
+    let unlocked = match prompt(conn, &prompt_path, cancellation).await? {
+        Some(result) => Vec::try_from(result).map_err(zbus::Error::from)?,
+        None => unlocked,
+    };
+    unlocked.into_iter().next().ok_or(SecretError::Locked)
+}
+
+/// Returns the default collection, creating it if necessary.
+async fn default_collection(
+    conn: &Connection,
+    service: &Proxy<'_>,
+    cancellation: &Cancellation,
+) -> Result<OwnedObjectPath, SecretError> {
+    let collection: OwnedObjectPath = service.call("ReadAlias", &("default",)).await?;
+    if collection.as_str() != "/" {
+        return Ok(collection);
+    }
+    let properties = HashMap::from([(
+        "org.freedesktop.Secret.Collection.Label",
+        Value::new("Login"),
+    )]);
+    let (collection, prompt_path): (OwnedObjectPath, OwnedObjectPath) = service
+        .call("CreateCollection", &(properties, "default"))
+        .await?;
+    let collection = match prompt(conn, &prompt_path, cancellation).await? {
+        Some(result) => OwnedObjectPath::try_from(result).map_err(zbus::Error::from)?,
+        None => collection,
+    };
+    if collection.as_str() == "/" {
+        return Err(SecretError::NoCollection);
+    }
+    Ok(collection)
+}
+
+/// Shows the prompt at `path` unless it is `/` and returns its result.
+///
+/// The prompt is dismissed if the request is cancelled before it completes.
+async fn prompt(
+    conn: &Connection,
+    path: &ObjectPath<'_>,
+    cancellation: &Cancellation,
+) -> Result<Option<OwnedValue>, SecretError> {
+    if path.as_str() == "/" {
+        return Ok(None);
+    }
+    let prompt = proxy(conn, path, PROMPT_INTERFACE).await?;
+    let mut completed = prompt.receive_signal("Completed").await?;
+    prompt.call_method("Prompt", &("",)).await?;
+    let msg = select! {
+        msg = completed.next().fuse() => msg,
+        _ = cancellation.cancelled().fuse() => {
+            if let Err(e) = prompt.call_method("Dismiss", &()).await {
+                log::warn!("Could not dismiss the secret prompt: {}", Report::new(e));
+            }
+            return Err(SecretError::Cancelled);
+        }
+    };
+    let Some(msg) = msg else {
+        return Err(SecretError::Dismissed);
+    };
+    let (dismissed, result): (bool, OwnedValue) = msg.body().deserialize()?;
+    if dismissed {
+        return Err(SecretError::Dismissed);
+    }
+    Ok(Some(result))
+}
+
+async fn proxy<'a>(
+    conn: &Connection,
+    path: &'a str,
+    interface: &'static str,
+) -> zbus::Result<Proxy<'a>> {
+    ProxyBuilder::<Proxy>::new(conn)
+        .destination(SECRETS_NAME)?
+        .path(path)?
+        .interface(interface)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await
+}
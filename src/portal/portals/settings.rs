@@ -0,0 +1,203 @@
+use {
+    crate::{gui::settings::Namespaces, portal::PATH},
+    async_channel::Receiver,
+    error_reporter::Report,
+    std::{collections::HashMap, sync::Mutex},
+    zbus::{
+        blocking::Connection,
+        interface,
+        object_server::SignalContext,
+        zvariant::{Array, OwnedValue, StructureBuilder, Value},
+        DBusError,
+    },
+};
+
+/// Exposes the settings received from a watcher.
+pub struct Settings {
+    current: Mutex<Namespaces>,
+    /// Closed once the first settings have been received.
+    ready: Receiver<()>,
+}
+
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "org.freedesktop.portal.Error")]
+enum SettingsError {
+    NotFound(String),
+}
+
+/// Adds the interface and updates it with the settings received from `updates`.
+///
+/// `SettingChanged` is emitted for every value that differs from the previous settings.
+/// Removed values are signaled with the zero value of their type. Method calls are
+/// delayed until the first settings have been received.
+pub fn serve(connection: &Connection, updates: Receiver<Namespaces>) -> zbus::Result<()> {
+    let (ready, ready_recv) = async_channel::bounded(1);
+    let settings = Settings {
+        current: Default::default(),
+        ready: ready_recv,
+    };
+    connection.object_server().at(PATH, settings)?;
+    let conn = connection.inner().clone();
+    let task = async move {
+        let iface = match conn.object_server().interface::<_, Settings>(PATH).await {
+            Ok(iface) => iface,
+            Err(e) => {
+                log::error!(
+                    "Could not access the settings interface: {}",
+                    Report::new(e)
+                );
+                return;
+            }
+        };
+        while let Ok(new) = updates.recv().await {
+            let changes = iface.get().await.update(new);
+            if ready.close() {
+                // the initial settings are not changes
+                continue;
+            }
+            for (namespace, key, value) in changes {
+                let res =
+                    Settings::setting_changed(iface.signal_context(), &namespace, &key, value)
+                        .await;
+                if let Err(e) = res {
+                    log::error!("Could not emit SettingChanged: {}", Report::new(e));
+                }
+            }
+        }
+    };
+    connection
+        .inner()
+        .executor()
+        .spawn(task, "settings updates")
+        .detach();
+    Ok(())
+}
+
+impl Settings {
+    /// Replaces the settings and returns the values that have changed or been removed.
+    fn update(&self, new: Namespaces) -> Vec<(String, String, OwnedValue)> {
+        let mut current = self.current.lock().unwrap();
+        let mut changes = vec![];
+        for (namespace, values) in &new {
+            let old = current.get(namespace);
+            for (key, value) in values {
+                if old.and_then(|o| o.get(key)) != Some(value) {
+                    changes.push((namespace.clone(), key.clone(), clone(value)));
+                }
+            }
+        }
+        for (namespace, values) in current.iter() {
+            let remaining = new.get(namespace);
+            for (key, value) in values {
+                if remaining.is_some_and(|r| r.contains_key(key)) {
+                    continue;
+                }
+                match unset(value) {
+                    Some(value) => changes.push((namespace.clone(), key.clone(), owned(value))),
+                    None => log::warn!("Cannot signal the removal of {namespace}.{key}"),
+                }
+            }
+        }
+        *current = new;
+        changes
+    }
+
+    async fn wait_ready(&self) {
+        let _ = self.ready.recv().await;
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Settings")]
+impl Settings {
+    async fn read_all(
+        &self,
+        namespaces: Vec<String>,
+    ) -> HashMap<String, HashMap<String, OwnedValue>> {
+        self.wait_ready().await;
+        let current = self.current.lock().unwrap();
+        current
+            .iter()
+            .filter(|(namespace, _)| {
+                namespaces.is_empty() || namespaces.iter().any(|p| matches(p, namespace))
+            })
+            .map(|(namespace, values)| {
+                let values = values.iter().map(|(k, v)| (k.clone(), clone(v))).collect();
+                (namespace.clone(), values)
+            })
+            .collect()
+    }
+
+    async fn read(&self, namespace: &str, key: &str) -> Result<OwnedValue, SettingsError> {
+        self.wait_ready().await;
+        let current = self.current.lock().unwrap();
+        match current.get(namespace).and_then(|v| v.get(key)) {
+            Some(value) => Ok(clone(value)),
+            None => Err(SettingsError::NotFound(format!(
+                "Requested setting {namespace}.{key} not found"
+            ))),
+        }
+    }
+
+    #[zbus(signal)]
+    async fn setting_changed(
+        ctxt: &SignalContext<'_>,
+        namespace: &str,
+        key: &str,
+        value: OwnedValue,
+    ) -> zbus::Result<()>;
+
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        2
+    }
+}
+
+/// Matches a namespace against a pattern that may end with `*`.
+fn matches(pattern: &str, namespace: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => namespace.starts_with(prefix),
+        None => pattern == namespace,
+    }
+}
+
+/// Returns the value that is signaled when a setting has been removed.
+///
+/// This is the zero value of the same type, e.g. no preference for the color scheme,
+/// except that doubles are -1 since out-of-range components leave the accent color unset.
+fn unset(value: &Value<'_>) -> Option<Value<'static>> {
+    let value = match value {
+        Value::Bool(_) => Value::Bool(false),
+        Value::U8(_) => Value::U8(0),
+        Value::I16(_) => Value::I16(0),
+        Value::U16(_) => Value::U16(0),
+        Value::I32(_) => Value::I32(0),
+        Value::U32(_) => Value::U32(0),
+        Value::I64(_) => Value::I64(0),
+        Value::U64(_) => Value::U64(0),
+        Value::F64(_) => Value::F64(-1.0),
+        Value::Str(_) => Value::from(String::new()),
+        Value::Value(v) => Value::Value(Box::new(unset(v)?)),
+        Value::Structure(s) => {
+            let mut builder = StructureBuilder::new();
+            for field in s.fields() {
+                builder = builder.append_field(unset(field)?);
+            }
+            Value::from(builder.build())
+        }
+        Value::Array(a) => Value::from(Array::new(a.element_signature().to_owned())),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn owned(value: Value<'_>) -> OwnedValue {
+    value
+        .try_to_owned()
+        .expect("settings do not contain file descriptors")
+}
+
+fn clone(value: &OwnedValue) -> OwnedValue {
+    value
+        .try_clone()
+        .expect("settings do not contain file descriptors")
+}
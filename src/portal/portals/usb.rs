@@ -0,0 +1,147 @@
+use {
+    crate::{
+        config::ConfigHandle,
+        gui::{
+            usb::{UsbDevice, UsbUi},
+            DialogBackend, DialogError,
+        },
+        portal::{request::run_request, response::Response},
+        utils::cancellation::Cancellation,
+    },
+    error_reporter::Report,
+    std::{collections::HashMap, sync::Arc},
+    zbus::{
+        interface,
+        zvariant::{OwnedObjectPath, OwnedValue, SerializeDict, Type},
+        Connection,
+    },
+};
+
+pub struct Usb {
+    backend: Arc<dyn DialogBackend<UsbUi>>,
+    config: ConfigHandle,
+}
+
+impl Usb {
+    pub fn new(backend: Arc<dyn DialogBackend<UsbUi>>, config: &ConfigHandle) -> Self {
+        Self {
+            backend,
+            config: config.clone(),
+        }
+    }
+}
+
+type Dict = HashMap<String, OwnedValue>;
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct AccessOptions {
+    writable: Option<bool>,
+}
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct AcquireDevicesResults {
+    devices: Option<Vec<(String, AccessOptions)>>,
+}
+
+impl Usb {
+    async fn acquire_devices_impl(
+        &self,
+        parent_window: String,
+        app_id: String,
+        devices: Vec<(String, Dict, Dict)>,
+        cancellation: Cancellation,
+    ) -> Response<AcquireDevicesResults> {
+        let devices: Vec<_> = devices.into_iter().map(parse_device).collect();
+        let allowed = if devices.is_empty() {
+            vec![]
+        } else {
+            let ui = UsbUi {
+                app_id,
+                devices: devices.clone(),
+                modal: self.config.get().modal,
+                parent_window,
+            };
+            match self.backend.run(ui, cancellation).await {
+                Ok(allowed) => allowed,
+                Err(e @ (DialogError::Rejected | DialogError::Cancelled)) => {
+                    log::debug!("AcquireDevices was not confirmed: {}", Report::new(e));
+                    return Response::cancelled();
+                }
+                Err(e) => {
+                    log::error!("AcquireDevices failed: {}", Report::new(e));
+                    return Response::failed();
+                }
+            }
+        };
+        let devices = devices
+            .into_iter()
+            .filter(|d| allowed.contains(&d.id))
+            .map(|d| {
+                let access = AccessOptions {
+                    writable: Some(d.writable),
+                };
+                (d.id, access)
+            })
+            .collect();
+        Response::success(AcquireDevicesResults {
+            devices: Some(devices),
+        })
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Usb")]
+impl Usb {
+    #[allow(clippy::too_many_arguments)]
+    async fn acquire_devices(
+        &self,
+        handle: OwnedObjectPath,
+        parent_window: String,
+        app_id: String,
+        devices: Vec<(String, Dict, Dict)>,
+        _options: Dict,
+        #[zbus(connection)] conn: &Connection,
+    ) -> Response<AcquireDevicesResults> {
+        run_request(conn, handle, |cancellation| {
+            self.acquire_devices_impl(parent_window, app_id, devices, cancellation)
+        })
+        .await
+    }
+
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        1
+    }
+}
+
+/// Parses a device from its ID, its information and the requested access.
+///
+/// The names are taken from the udev properties in the information. Values of the
+/// wrong type are ignored.
+fn parse_device((id, mut info, mut access): (String, Dict, Dict)) -> UsbDevice {
+    let mut properties = info
+        .remove("properties")
+        .and_then(|p| Dict::try_from(p).ok())
+        .unwrap_or_default();
+    let mut name = |database: &str, fallback: &str| {
+        string(&mut properties, database)
+            .or_else(|| Some(string(&mut properties, fallback)?.replace('_', " ")))
+    };
+    let vendor = name("ID_VENDOR_FROM_DATABASE", "ID_VENDOR");
+    let product = name("ID_MODEL_FROM_DATABASE", "ID_MODEL");
+    UsbDevice {
+        id,
+        vendor,
+        product,
+        serial: string(&mut properties, "ID_SERIAL_SHORT"),
+        writable: access
+            .remove("writable")
+            .and_then(|w| bool::try_from(w).ok())
+            .unwrap_or(false),
+    }
+}
+
+fn string(dict: &mut Dict, key: &str) -> Option<String> {
+    dict.remove(key).and_then(|v| String::try_from(v).ok())
+}
@@ -0,0 +1,165 @@
+use {
+    crate::{
+        config::ConfigHandle,
+        gui::{
+            wallpaper::{PreviewUi, SetOn},
+            DialogBackend, DialogError,
+        },
+        portal::{request::run_request, response::Response},
+        utils::{
+            cancellation::Cancellation,
+            wallpaper::{Target, WallpaperSetter},
+        },
+    },
+    error_reporter::Report,
+    std::{
+        ffi::OsString,
+        os::unix::ffi::{OsStrExt, OsStringExt},
+        path::Path,
+        sync::Arc,
+    },
+    url::Url,
+    zbus::{
+        interface,
+        zvariant::{DeserializeDict, OwnedObjectPath, Type},
+        Connection,
+    },
+};
+
+pub struct Wallpaper {
+    previews: Arc<dyn DialogBackend<PreviewUi>>,
+    setter: Arc<dyn WallpaperSetter>,
+    config: ConfigHandle,
+}
+
+impl Wallpaper {
+    pub fn new(
+        previews: Arc<dyn DialogBackend<PreviewUi>>,
+        setter: Arc<dyn WallpaperSetter>,
+        config: &ConfigHandle,
+    ) -> Self {
+        Self {
+            previews,
+            setter,
+            config: config.clone(),
+        }
+    }
+}
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct SetWallpaperOptions {
+    #[zvariant(rename = "show-preview")]
+    show_preview: Option<bool>,
+    #[zvariant(rename = "set-on")]
+    set_on: Option<String>,
+}
+
+impl Wallpaper {
+    async fn set_wallpaper_uri_impl(
+        &self,
+        app_id: String,
+        parent_window: String,
+        uri: String,
+        options: SetWallpaperOptions,
+        cancellation: Cancellation,
+    ) -> Response<()> {
+        let Some(path) = Url::parse(&uri).ok().and_then(|u| u.to_file_path().ok()) else {
+            log::error!("Cannot set {uri} as the wallpaper: Only local files are supported");
+            return Response::failed();
+        };
+        let set_on = match options.set_on.as_deref() {
+            None | Some("both") => SetOn::Both,
+            Some("background") => SetOn::Background,
+            Some("lockscreen") => SetOn::Lockscreen,
+            Some(s) => {
+                log::error!("Unknown wallpaper target {s}");
+                return Response::failed();
+            }
+        };
+        let config = self.config.get();
+        let wallpaper = &config.wallpaper;
+        // For both targets, the targets that have a command are set.
+        let background = (Target::Background, &wallpaper.background_command);
+        let lockscreen = (Target::Lockscreen, &wallpaper.lockscreen_command);
+        let commands: Vec<_> = match set_on {
+            SetOn::Background => vec![background],
+            SetOn::Lockscreen => vec![lockscreen],
+            SetOn::Both => vec![background, lockscreen],
+        }
+        .into_iter()
+        .filter_map(|(target, c)| Some((target, substitute(c.as_ref()?, &path))))
+        .collect();
+        if commands.is_empty() {
+            log::error!("Cannot set the wallpaper: No command is configured for {set_on:?}");
+            return Response::failed();
+        }
+        if options.show_preview.unwrap_or(false) {
+            let ui = PreviewUi {
+                app_id,
+                path,
+                set_on,
+                modal: config.modal,
+                parent_window,
+            };
+            match self.previews.run(ui, cancellation).await {
+                Ok(()) => {}
+                Err(e @ (DialogError::Rejected | DialogError::Cancelled)) => {
+                    log::debug!("SetWallpaperURI was not confirmed: {}", Report::new(e));
+                    return Response::cancelled();
+                }
+                Err(e) => {
+                    log::error!("SetWallpaperURI failed: {}", Report::new(e));
+                    return Response::failed();
+                }
+            }
+        }
+        for (target, command) in commands {
+            if let Err(e) = self.setter.set(target, command) {
+                log::error!("Could not set the wallpaper: {}", Report::new(e));
+                return Response::failed();
+            }
+        }
+        Response::success(())
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Wallpaper")]
+impl Wallpaper {
+    #[allow(clippy::too_many_arguments)]
+    #[zbus(name = "SetWallpaperURI")]
+    async fn set_wallpaper_uri(
+        &self,
+        handle: OwnedObjectPath,
+        app_id: String,
+        parent_window: String,
+        uri: String,
+        options: SetWallpaperOptions,
+        #[zbus(connection)] conn: &Connection,
+    ) -> u32 {
+        let response = run_request(conn, handle, |cancellation| {
+            self.set_wallpaper_uri_impl(app_id, parent_window, uri, options, cancellation)
+        })
+        .await;
+        response.code()
+    }
+}
+
+/// Replaces `%f` in the arguments of a command with `path`.
+fn substitute(command: &[OsString], path: &Path) -> Vec<OsString> {
+    let path = path.as_os_str().as_bytes();
+    command
+        .iter()
+        .map(|arg| {
+            let mut res = vec![];
+            let mut rest = arg.as_bytes();
+            while let Some(pos) = rest.windows(2).position(|w| w == b"%f") {
+                res.extend_from_slice(&rest[..pos]);
+                res.extend_from_slice(path);
+                rest = &rest[pos + 2..];
+            }
+            res.extend_from_slice(rest);
+            OsString::from_vec(res)
+        })
+        .collect()
+}
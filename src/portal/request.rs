@@ -1,47 +1,92 @@
 use {
-    crate::portal::response::Response,
-    async_channel::Sender,
+    crate::{
+        portal::response::Response,
+        utils::cancellation::{cancellation, Cancellation, CancellationSource},
+    },
     error_reporter::Report,
-    futures_util::{select, FutureExt},
     std::future::Future,
     zbus::{
         interface,
         zvariant::{OwnedObjectPath, Type},
-        ObjectServer,
+        Connection,
     },
 };
 
-async fn export_request(server: &ObjectServer, path: OwnedObjectPath) {
-    let (send, recv) = async_channel::bounded(1);
-    if let Err(e) = server.at(&path, Request { send }).await {
-        log::error!("Could not export request object: {}", Report::new(e));
-        return;
-    }
-    let _ = recv.recv().await;
-    let _ = server.remove::<Request, _>(&path).await;
-}
-
-/// Runs the future to completion or exits early if the request is closed.
+/// Runs the future to completion.
 ///
-/// This is inherently racy because the request might get cancelled before we export the
-/// path.
-pub async fn run_request<T, F>(server: &ObjectServer, handle: OwnedObjectPath, f: F) -> Response<T>
+/// The request object is exported before the future is created and removed once it
+/// completes or this future is dropped. If the request is closed or removed, the
+/// [`Cancellation`] passed to `f` is cancelled. The future is expected to tear down any
+/// UI it has started and to complete promptly with [`Response::cancelled`] in that case.
+pub async fn run_request<T, F, Fut>(conn: &Connection, handle: OwnedObjectPath, f: F) -> Response<T>
 where
     T: Default + Type,
-    F: Future<Output = Response<T>>,
+    F: FnOnce(Cancellation) -> Fut,
+    Fut: Future<Output = Response<T>>,
 {
-    select! {
-        v = f.fuse() => v,
-        _ = export_request(server, handle).fuse() => Response::cancelled(),
+    let (cancel, cancellation) = cancellation();
+    let request = Request { cancel };
+    match conn.object_server().at(&handle, request).await {
+        Ok(true) => {}
+        Ok(false) => {
+            log::error!("Request object {} already exists", handle.as_str());
+            return Response::failed();
+        }
+        Err(e) => {
+            log::error!("Could not export request object: {}", Report::new(e));
+            return Response::failed();
+        }
     }
+    let exported = Exported {
+        conn: conn.clone(),
+        handle: Some(handle),
+    };
+    let res = f(cancellation).await;
+    exported.remove().await;
+    res
 }
 
+/// An exported request object. If this is dropped before [`Exported::remove`] has
+/// completed, the object is removed in the background.
+struct Exported {
+    conn: Connection,
+    handle: Option<OwnedObjectPath>,
+}
+
+impl Exported {
+    async fn remove(mut self) {
+        if let Some(handle) = &self.handle {
+            let _ = self.conn.object_server().remove::<Request, _>(handle).await;
+            self.handle = None;
+        }
+    }
+}
+
+impl Drop for Exported {
+    fn drop(&mut self) {
+        let Some(handle) = self.handle.take() else {
+            return;
+        };
+        let conn = self.conn.clone();
+        let remove = async move {
+            let _ = conn.object_server().remove::<Request, _>(&handle).await;
+        };
+        self.conn
+            .executor()
+            .spawn(remove, "remove request")
+            .detach();
+    }
+}
+
+/// Removing the object drops the only [`CancellationSource`] of the request, which
+/// cancels it.
 struct Request {
-    send: Sender<()>,
+    cancel: CancellationSource,
 }
+
 #[interface(name = "org.freedesktop.impl.portal.Request")]
 impl Request {
     async fn close(&self) {
-        let _ = self.send.send(()).await;
+        self.cancel.cancel();
     }
 }
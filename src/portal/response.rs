@@ -2,6 +2,7 @@ use {serde::Serialize, zbus::zvariant::Type};
 
 const PORTAL_SUCCESS: u32 = 0;
 const PORTAL_CANCELLED: u32 = 1;
+const PORTAL_OTHER: u32 = 2;
 
 #[derive(Serialize, Type)]
 pub struct Response<T: Type>(u32, T);
@@ -17,4 +18,17 @@ impl<T: Type> Response<T> {
     {
         Self(PORTAL_CANCELLED, T::default())
     }
+
+    /// The interaction ended in some other way than a user cancellation, e.g. an error.
+    pub fn failed() -> Self
+    where
+        T: Default,
+    {
+        Self(PORTAL_OTHER, T::default())
+    }
+
+    /// Returns the response code, for methods that do not return results.
+    pub fn code(&self) -> u32 {
+        self.0
+    }
 }
@@ -0,0 +1,186 @@
+use {
+    crate::utils::cancellation::{cancellation, Cancellation, CancellationSource},
+    error_reporter::Report,
+    futures_util::{
+        future::{select, Either},
+        StreamExt,
+    },
+    std::{
+        collections::{hash_map::Entry, HashMap},
+        pin::pin,
+        sync::{
+            atomic::{AtomicU64, Ordering::Relaxed},
+            Arc, Mutex,
+        },
+    },
+    zbus::{
+        fdo::DBusProxy,
+        interface,
+        message::Header,
+        names::{BusName, UniqueName},
+        object_server::SignalContext,
+        zvariant::{ObjectPath, OwnedObjectPath},
+        Connection,
+    },
+};
+
+/// The sessions of an interface and their state.
+///
+/// A session is closed when the client closes it or when the client disconnects from
+/// the bus. Its state is dropped at that point. In the latter case, the backend closes
+/// the session and emits `Closed`.
+pub struct Sessions<T> {
+    /// The state of each session, tagged with the id of the session object that owns it.
+    state: Arc<Mutex<HashMap<OwnedObjectPath, (u64, T)>>>,
+}
+
+impl<T> Clone for Sessions<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T> Default for Sessions<T> {
+    fn default() -> Self {
+        Self {
+            state: Default::default(),
+        }
+    }
+}
+
+impl<T: Send + 'static> Sessions<T> {
+    /// Exports a session object at `handle` that belongs to `peer`.
+    ///
+    /// The returned [`Cancellation`] is cancelled once the session is closed.
+    pub async fn create(
+        &self,
+        conn: &Connection,
+        handle: OwnedObjectPath,
+        peer: Option<UniqueName<'_>>,
+        state: T,
+    ) -> zbus::Result<Cancellation> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, Relaxed);
+        let exists = || zbus::Error::Failure(format!("Session {} already exists", handle.as_str()));
+        // The state is stored before the object is exported so that it is available as
+        // soon as the client can use the session.
+        match self.state.lock().unwrap().entry(handle.clone()) {
+            Entry::Occupied(_) => return Err(exists()),
+            Entry::Vacant(e) => {
+                e.insert((id, state));
+            }
+        }
+        let (cancel, cancellation) = cancellation();
+        let sessions = self.clone();
+        let key = handle.clone();
+        let session = Session {
+            _cancel: cancel,
+            cleanup: Some(Box::new(move || sessions.remove(&key, id))),
+        };
+        // If the object cannot be exported, the session is dropped here, which drops its
+        // state again.
+        if !conn.object_server().at(&handle, session).await? {
+            return Err(exists());
+        }
+        if let Some(peer) = peer {
+            let task =
+                close_on_disconnect(conn.clone(), handle, peer.to_owned(), cancellation.clone());
+            conn.executor().spawn(task, "session peer").detach();
+        }
+        Ok(cancellation)
+    }
+
+    /// Calls `f` with the state of a session if the session is still open.
+    pub fn with<R>(&self, handle: &OwnedObjectPath, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        self.state
+            .lock()
+            .unwrap()
+            .get_mut(handle)
+            .map(|(_, state)| f(state))
+    }
+
+    /// Drops the state of the session object `id`.
+    fn remove(&self, handle: &OwnedObjectPath, id: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.get(handle).is_some_and(|(i, _)| *i == id) {
+            state.remove(handle);
+        }
+    }
+}
+
+/// Removes the session object once `peer` has disconnected from the bus.
+async fn close_on_disconnect(
+    conn: Connection,
+    handle: OwnedObjectPath,
+    peer: UniqueName<'static>,
+    cancellation: Cancellation,
+) {
+    let disconnected = async {
+        let dbus = DBusProxy::new(&conn).await?;
+        let mut changes = dbus
+            .receive_name_owner_changed_with_args(&[(0, peer.as_str()), (2, "")])
+            .await?;
+        if dbus.name_has_owner(BusName::from(peer.as_ref())).await? {
+            changes.next().await;
+        }
+        Ok::<_, zbus::Error>(())
+    };
+    let res = match select(pin!(cancellation.cancelled()), pin!(disconnected)).await {
+        Either::Left(_) => return,
+        Either::Right((res, _)) => res,
+    };
+    if let Err(e) = res {
+        log::error!("Could not watch the session peer: {}", Report::new(e));
+        return;
+    }
+    log::debug!("Closing {} since {peer} disconnected", handle.as_str());
+    if let Err(e) = close(&conn, &handle).await {
+        log::error!("Could not close {}: {}", handle.as_str(), Report::new(e));
+    }
+}
+
+/// Removes a session object and emits `Closed`.
+async fn close(conn: &Connection, handle: &ObjectPath<'_>) -> zbus::Result<()> {
+    let ctxt = SignalContext::new(conn, handle)?;
+    if conn.object_server().remove::<Session, _>(handle).await? {
+        Session::closed(&ctxt).await?;
+    }
+    Ok(())
+}
+
+struct Session {
+    _cancel: CancellationSource,
+    /// Drops the state of the session.
+    cleanup: Option<Box<dyn FnOnce() + Send + Sync>>,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if let Some(cleanup) = self.cleanup.take() {
+            cleanup();
+        }
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Session")]
+impl Session {
+    async fn close(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) {
+        if let Some(path) = header.path() {
+            let _ = conn.object_server().remove::<Self, _>(path).await;
+        }
+    }
+
+    #[zbus(signal)]
+    async fn closed(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        1
+    }
+}
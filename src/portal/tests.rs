@@ -0,0 +1,20 @@
+mod access;
+mod account;
+mod app_chooser;
+mod backends;
+mod background;
+mod dynamic_launcher;
+mod email;
+mod file_chooser;
+mod harness;
+mod inhibit;
+mod lockdown;
+mod notification;
+mod print;
+mod request;
+mod screenshot;
+mod secret;
+mod session;
+mod settings;
+mod usb;
+mod wallpaper;
@@ -0,0 +1,148 @@
+use {
+    crate::{
+        config::Config,
+        gui::{
+            access::{AccessResult, AccessUi},
+            file_chooser::{Choice, ChoiceVariant, FinalChoice},
+            DialogError,
+        },
+        portal::{
+            portals::access::Access,
+            tests::{
+                backends::Scripted,
+                harness::{handle, Options, Response, TestPortal, CANCELLED, SUCCESS},
+            },
+        },
+    },
+    std::sync::Arc,
+    zbus::zvariant::{DeserializeDict, Type, Value},
+};
+
+const ACCESS: &str = "org.freedesktop.impl.portal.Access";
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct AccessDialogResults {
+    choices: Option<Vec<(String, String)>>,
+}
+
+fn access_dialog<R>(portal: &TestPortal, options: Options) -> (u32, R)
+where
+    R: serde::de::DeserializeOwned + Type,
+{
+    portal
+        .call(
+            ACCESS,
+            "AccessDialog",
+            &(
+                handle(),
+                "org.example.App",
+                "",
+                "Allow camera access?",
+                "Example wants to use your camera.",
+                "Access can be changed at any time from the privacy settings.",
+                options,
+            ),
+        )
+        .unwrap()
+}
+
+#[test]
+fn access_dialog_maps_options_and_choices() {
+    let backend = Arc::new(Scripted::<AccessUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Access::new(backend.clone(), p.config())
+    });
+    backend.push(Ok(AccessResult {
+        final_choices: vec![FinalChoice {
+            id: "remember".to_string(),
+            variant_id: "true".to_string(),
+        }],
+    }));
+    let mut options = Options::new();
+    options.insert("modal", Value::new(false));
+    options.insert("deny_label", Value::new("Don't Allow"));
+    options.insert("grant_label", Value::new("Allow"));
+    options.insert("icon", Value::new("camera-web-symbolic"));
+    let choices = vec![
+        ("remember", "Remember", Vec::<(&str, &str)>::new(), "false"),
+        (
+            "camera",
+            "Camera",
+            vec![("front", "Front"), ("back", "Back")],
+            "back",
+        ),
+    ];
+    options.insert("choices", Value::new(choices));
+    let (response, results): (_, AccessDialogResults) = access_dialog(&portal, options);
+    assert_eq!(response, SUCCESS);
+    assert_eq!(
+        results.choices,
+        Some(vec![("remember".to_string(), "true".to_string())]),
+    );
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(ui.app_id, "org.example.App");
+    assert_eq!(ui.title, "Allow camera access?");
+    assert_eq!(ui.subtitle, "Example wants to use your camera.");
+    assert!(!ui.modal);
+    assert_eq!(ui.deny_label.as_deref(), Some("Don't Allow"));
+    assert_eq!(ui.grant_label.as_deref(), Some("Allow"));
+    assert_eq!(ui.icon.as_deref(), Some("camera-web-symbolic"));
+    assert_eq!(ui.choices.len(), 2);
+    assert_eq!(
+        ui.choices[1],
+        Choice {
+            id: "camera".to_string(),
+            label: "Camera".to_string(),
+            default: "back".to_string(),
+            variants: vec![
+                ChoiceVariant {
+                    id: "front".to_string(),
+                    label: "Front".to_string(),
+                },
+                ChoiceVariant {
+                    id: "back".to_string(),
+                    label: "Back".to_string(),
+                },
+            ],
+        },
+    );
+}
+
+#[test]
+fn denied_access_is_cancelled() {
+    let backend = Arc::new(Scripted::<AccessUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Access::new(backend.clone(), p.config())
+    });
+    backend.push(Err(DialogError::Rejected));
+    let (response, results): Response = access_dialog(&portal, Options::new());
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+}
+
+#[test]
+fn close_cancels_access_dialog() {
+    let backend = Arc::new(Scripted::<AccessUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Access::new(backend.clone(), p.config())
+    });
+    let handle = handle();
+    let pending = portal.spawn_call::<_, Response>(
+        ACCESS,
+        "AccessDialog",
+        (
+            handle.clone(),
+            "org.example.App",
+            "",
+            "Title",
+            "",
+            "",
+            Options::new(),
+        ),
+    );
+    portal.close(&handle);
+    let (response, results) = pending.join().unwrap().unwrap();
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+}
@@ -0,0 +1,103 @@
+use {
+    crate::{
+        config::Config,
+        gui::{account::AccountUi, DialogError},
+        portal::{
+            portals::account::{current_user, Account},
+            tests::{
+                backends::Scripted,
+                harness::{handle, Options, Response, TestPortal, CANCELLED, SUCCESS},
+            },
+        },
+    },
+    std::{fs, sync::Arc},
+    url::Url,
+    zbus::zvariant::{DeserializeDict, Type, Value},
+};
+
+const ACCOUNT: &str = "org.freedesktop.impl.portal.Account";
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct GetUserInformationResults {
+    id: Option<String>,
+    name: Option<String>,
+    image: Option<String>,
+}
+
+fn get_user_information<R>(portal: &TestPortal) -> (u32, R)
+where
+    R: serde::de::DeserializeOwned + Type,
+{
+    let mut options = Options::new();
+    options.insert("reason", Value::new("To sign your commits"));
+    portal
+        .call(
+            ACCOUNT,
+            "GetUserInformation",
+            &(handle(), "org.example.App", "", options),
+        )
+        .unwrap()
+}
+
+#[test]
+fn confirmed_requests_return_the_user() {
+    let backend = Arc::new(Scripted::<AccountUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Account::new(backend.clone(), p.config())
+    });
+    backend.push(Ok(()));
+    let (response, results): (_, GetUserInformationResults) = get_user_information(&portal);
+    assert_eq!(response, SUCCESS);
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(ui.app_id, "org.example.App");
+    assert_eq!(ui.reason.as_deref(), Some("To sign your commits"));
+    assert_eq!(results.id, Some(ui.user.id));
+    assert_eq!(results.name, Some(ui.user.name));
+    let image = ui
+        .user
+        .image
+        .map(|p| Url::from_file_path(p).unwrap().to_string());
+    assert_eq!(results.image, image);
+}
+
+#[test]
+fn rejected_requests_return_nothing() {
+    let backend = Arc::new(Scripted::<AccountUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Account::new(backend.clone(), p.config())
+    });
+    backend.push(Err(DialogError::Rejected));
+    let (response, results): Response = get_user_information(&portal);
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+}
+
+#[test]
+fn close_cancels_get_user_information() {
+    let backend = Arc::new(Scripted::<AccountUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Account::new(backend.clone(), p.config())
+    });
+    let handle = handle();
+    let pending = portal.spawn_call::<_, Response>(
+        ACCOUNT,
+        "GetUserInformation",
+        (handle.clone(), "org.example.App", "", Options::new()),
+    );
+    portal.close(&handle);
+    let (response, results) = pending.join().unwrap().unwrap();
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+}
+
+#[test]
+fn accounts_service_icons_take_precedence() {
+    let icons = tempfile::tempdir().unwrap();
+    let user = current_user(icons.path()).unwrap();
+    assert!(!user.id.is_empty());
+    assert!(!user.name.is_empty());
+    let icon = icons.path().join(&user.id);
+    fs::write(&icon, "").unwrap();
+    assert_eq!(current_user(icons.path()).unwrap().image, Some(icon));
+}
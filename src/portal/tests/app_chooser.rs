@@ -0,0 +1,160 @@
+use {
+    crate::{
+        config::Config,
+        gui::app_chooser::{AppChooserResult, AppChooserUi},
+        portal::{
+            portals::app_chooser::AppChooser,
+            tests::{
+                backends::Scripted,
+                harness::{handle, Options, Response, TestPortal, CANCELLED, SUCCESS},
+            },
+        },
+    },
+    std::sync::Arc,
+    zbus::zvariant::Value,
+};
+
+const APP_CHOOSER: &str = "org.freedesktop.impl.portal.AppChooser";
+
+#[test]
+fn close_cancels_choose_application() {
+    let portal = TestPortal::new();
+    let handle = handle();
+    let mut options = Options::new();
+    options.insert("content_type", Value::new("text/plain"));
+    options.insert("last_choice", Value::new("org.example.Editor"));
+    let pending = portal.spawn_call::<_, Response>(
+        APP_CHOOSER,
+        "ChooseApplication",
+        (
+            handle.clone(),
+            "org.example.App",
+            "",
+            vec!["org.example.Editor"],
+            options,
+        ),
+    );
+    portal.close(&handle);
+    let (response, results) = pending.join().unwrap().unwrap();
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+    assert!(!portal.request_exists(&handle));
+}
+
+/// Closing the request cancels the dialog that is being shown. The scripted dialog only
+/// completes when it is cancelled, so the response shows that the cancellation reached it.
+#[test]
+fn close_dismisses_the_dialog() {
+    let backend = Arc::new(Scripted::<AppChooserUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        AppChooser::new(backend.clone(), p.config())
+    });
+    let handle = handle();
+    let pending = portal.spawn_call::<_, Response>(
+        APP_CHOOSER,
+        "ChooseApplication",
+        (
+            handle.clone(),
+            "org.example.App",
+            "",
+            vec!["org.example.Editor"],
+            Options::new(),
+        ),
+    );
+    portal.wait_for_request(&handle);
+    portal.close(&handle);
+    let (response, results) = pending.join().unwrap().unwrap();
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+    assert_eq!(backend.dialogs().len(), 1);
+    assert!(!portal.request_exists(&handle));
+}
+
+#[test]
+fn choice_is_returned() {
+    let backend = Arc::new(Scripted::<AppChooserUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        AppChooser::new(backend.clone(), p.config())
+    });
+    backend.push(Ok(AppChooserResult {
+        choice: "org.example.Viewer".to_string(),
+    }));
+    let mut options = Options::new();
+    options.insert("content_type", Value::new("image/png"));
+    options.insert("last_choice", Value::new("org.example.Editor"));
+    options.insert("modal", Value::new(false));
+    let (response, results): Response = portal
+        .call(
+            APP_CHOOSER,
+            "ChooseApplication",
+            &(
+                handle(),
+                "org.example.App",
+                "x11:1f",
+                vec!["org.example.Editor", "org.example.Viewer"],
+                options,
+            ),
+        )
+        .unwrap();
+    assert_eq!(response, SUCCESS);
+    assert_eq!(
+        <&str>::try_from(&results["choice"]).unwrap(),
+        "org.example.Viewer"
+    );
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(ui.choices, ["org.example.Editor", "org.example.Viewer"]);
+    assert_eq!(ui.last_choice.as_deref(), Some("org.example.Editor"));
+    assert_eq!(ui.content_type.as_deref(), Some("image/png"));
+    assert!(!ui.modal);
+    assert_eq!(ui.parent_window, "x11:1f");
+}
+
+#[test]
+fn update_choices_reach_the_dialog() {
+    let backend = Arc::new(Scripted::<AppChooserUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        AppChooser::new(backend.clone(), p.config())
+    });
+    let handle = handle();
+    let pending = portal.spawn_call::<_, Response>(
+        APP_CHOOSER,
+        "ChooseApplication",
+        (
+            handle.clone(),
+            "org.example.App",
+            "",
+            vec!["org.example.Editor"],
+            Options::new(),
+        ),
+    );
+    portal.wait_for_request(&handle);
+    portal
+        .call::<_, ()>(
+            APP_CHOOSER,
+            "UpdateChoices",
+            &(&handle, vec!["org.example.Editor", "org.example.Viewer"]),
+        )
+        .unwrap();
+    portal.close(&handle);
+    let (response, _) = pending.join().unwrap().unwrap();
+    assert_eq!(response, CANCELLED);
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(ui.choices, ["org.example.Editor"]);
+    assert_eq!(
+        ui.updates.try_recv().unwrap(),
+        ["org.example.Editor", "org.example.Viewer"]
+    );
+    assert!(ui.updates.try_recv().is_err());
+}
+
+#[test]
+fn update_choices_of_unknown_request() {
+    let portal = TestPortal::new();
+    portal
+        .call::<_, ()>(
+            APP_CHOOSER,
+            "UpdateChoices",
+            &(handle(), vec!["org.example.Editor"]),
+        )
+        .unwrap();
+}
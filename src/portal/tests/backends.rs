@@ -0,0 +1,170 @@
+use {
+    crate::{
+        gui::{
+            email::{Draft, EmailError, MailClient},
+            file_chooser::{
+                FileChooserBackend, FileChooserError, FileChooserResult, FileChooserUi,
+            },
+            print::{PrintBackend, PrintDocument, PrintError, PrintSetup, PrintUi},
+            wallpaper::PreviewUi,
+            DialogBackend, DialogError, DialogUi,
+        },
+        utils::{
+            cancellation::Cancellation,
+            wallpaper::{Target, WallpaperError, WallpaperSetter},
+        },
+    },
+    futures_util::{future::BoxFuture, FutureExt},
+    std::{
+        collections::VecDeque,
+        ffi::OsString,
+        fs::File,
+        io,
+        sync::{
+            atomic::{AtomicBool, Ordering::Relaxed},
+            Mutex,
+        },
+    },
+    url::Url,
+};
+
+/// Dialogs that return scripted results.
+///
+/// Every dialog that is started is recorded. Dialogs for which no result has been queued
+/// only complete when they are cancelled.
+pub struct Script<U, R> {
+    results: Mutex<VecDeque<R>>,
+    dialogs: Mutex<Vec<U>>,
+}
+
+impl<U, R> Default for Script<U, R> {
+    fn default() -> Self {
+        Self {
+            results: Default::default(),
+            dialogs: Default::default(),
+        }
+    }
+}
+
+impl<U, R: Send + 'static> Script<U, R> {
+    /// Queues the result of the next dialog.
+    pub fn push(&self, res: R) {
+        self.results.lock().unwrap().push_back(res);
+    }
+
+    /// Returns the dialogs that have been started since the last call.
+    pub fn dialogs(&self) -> Vec<U> {
+        std::mem::take(&mut *self.dialogs.lock().unwrap())
+    }
+
+    fn show(&self, ui: U, cancellation: Cancellation, cancelled: R) -> BoxFuture<'static, R> {
+        self.dialogs.lock().unwrap().push(ui);
+        let res = self.results.lock().unwrap().pop_front();
+        async move {
+            match res {
+                Some(res) => res,
+                None => {
+                    cancellation.cancelled().await;
+                    cancelled
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+pub type ScriptedFileChooser = Script<FileChooserUi, Result<FileChooserResult, FileChooserError>>;
+
+impl FileChooserBackend for ScriptedFileChooser {
+    fn run(
+        &self,
+        ui: FileChooserUi,
+        cancellation: Cancellation,
+    ) -> BoxFuture<'static, Result<FileChooserResult, FileChooserError>> {
+        self.show(ui, cancellation, Err(FileChooserError::Cancelled))
+    }
+}
+
+/// Scripted dialogs of type `U`.
+pub type Scripted<U> = Script<U, Result<<U as DialogUi>::Output, DialogError>>;
+
+impl<U: DialogUi> DialogBackend<U> for Scripted<U> {
+    fn run(
+        &self,
+        ui: U,
+        cancellation: Cancellation,
+    ) -> BoxFuture<'static, Result<U::Output, DialogError>> {
+        self.show(ui, cancellation, Err(DialogError::Cancelled))
+    }
+}
+
+/// Scripted print dialogs. Documents are written to the file named by the `output-uri`
+/// setting.
+pub type PrintToFile = Script<PrintUi, Result<PrintSetup, PrintError>>;
+
+impl PrintBackend for PrintToFile {
+    fn prepare(
+        &self,
+        ui: PrintUi,
+        cancellation: Cancellation,
+    ) -> BoxFuture<'static, Result<PrintSetup, PrintError>> {
+        self.show(ui, cancellation, Err(PrintError::Cancelled))
+    }
+
+    fn print(&self, document: PrintDocument) -> BoxFuture<'static, Result<(), PrintError>> {
+        async move {
+            let uri = document
+                .setup
+                .settings
+                .get("output-uri")
+                .ok_or(PrintError::NoPrinter)?;
+            let path = Url::parse(uri).unwrap().to_file_path().unwrap();
+            let mut output = File::create(path).unwrap();
+            io::copy(&mut File::from(document.fd), &mut output).unwrap();
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// A mail client that records drafts instead of starting a mail client.
+#[derive(Default)]
+pub struct FakeMailClient {
+    pub drafts: Mutex<Vec<Draft>>,
+    pub fail: AtomicBool,
+}
+
+impl MailClient for FakeMailClient {
+    fn compose(&self, draft: Draft) -> BoxFuture<'static, Result<(), EmailError>> {
+        self.drafts.lock().unwrap().push(draft);
+        let res = match self.fail.load(Relaxed) {
+            true => Err(EmailError::EmptyCommand),
+            false => Ok(()),
+        };
+        async move { res }.boxed()
+    }
+}
+
+/// Scripted wallpaper previews. The commands that would set the wallpaper are recorded.
+#[derive(Default)]
+pub struct FakeWallpaper {
+    pub previews: Scripted<PreviewUi>,
+    pub commands: Mutex<Vec<(Target, Vec<OsString>)>>,
+}
+
+impl DialogBackend<PreviewUi> for FakeWallpaper {
+    fn run(
+        &self,
+        ui: PreviewUi,
+        cancellation: Cancellation,
+    ) -> BoxFuture<'static, Result<(), DialogError>> {
+        self.previews.run(ui, cancellation)
+    }
+}
+
+impl WallpaperSetter for FakeWallpaper {
+    fn set(&self, target: Target, command: Vec<OsString>) -> Result<(), WallpaperError> {
+        self.commands.lock().unwrap().push((target, command));
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use {
+    crate::{
+        config::Config,
+        gui::background::BackgroundUi,
+        portal::{
+            portals::background::Background,
+            tests::{
+                backends::Scripted,
+                harness::{handle, Response, TestPortal, CANCELLED, SUCCESS},
+            },
+        },
+    },
+    std::{collections::HashMap, fs, sync::Arc},
+    zbus::zvariant::OwnedValue,
+};
+
+const BACKGROUND: &str = "org.freedesktop.impl.portal.Background";
+
+fn notify_background(portal: &TestPortal) -> Response {
+    portal
+        .call(
+            BACKGROUND,
+            "NotifyBackground",
+            &(handle(), "org.example.Chat", "Chat"),
+        )
+        .unwrap()
+}
+
+fn enable_autostart(portal: &TestPortal, app_id: &str, enable: bool, commandline: &[&str]) -> bool {
+    portal
+        .call(
+            BACKGROUND,
+            "EnableAutostart",
+            &(app_id, enable, commandline, 1u32),
+        )
+        .unwrap()
+}
+
+#[test]
+fn background_activity_can_be_allowed_and_denied() {
+    let backend = Arc::new(Scripted::<BackgroundUi>::default());
+    let dir = tempfile::tempdir().unwrap();
+    let portal = TestPortal::with(Config::default(), |_| {
+        Background::with_autostart_dir(backend.clone(), dir.path().into())
+    });
+    for (allow, expected) in [(true, 1u32), (false, 0)] {
+        backend.push(Ok(allow));
+        let (response, results) = notify_background(&portal);
+        assert_eq!(response, SUCCESS);
+        assert_eq!(u32::try_from(&results["result"]).unwrap(), expected);
+    }
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(ui.app_id, "org.example.Chat");
+    assert_eq!(ui.name, "Chat");
+}
+
+#[test]
+fn unanswered_notifications_are_cancelled() {
+    let backend = Arc::new(Scripted::<BackgroundUi>::default());
+    let dir = tempfile::tempdir().unwrap();
+    let portal = TestPortal::with(Config::default(), |_| {
+        Background::with_autostart_dir(backend.clone(), dir.path().into())
+    });
+    let handle = handle();
+    let pending = portal.spawn_call::<_, Response>(
+        BACKGROUND,
+        "NotifyBackground",
+        (handle.clone(), "org.example.Chat", "Chat"),
+    );
+    portal.close(&handle);
+    let (response, results) = pending.join().unwrap().unwrap();
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+}
+
+#[test]
+fn autostart_files_are_written_and_removed() {
+    let dir = tempfile::tempdir().unwrap();
+    let autostart = dir.path().join("autostart");
+    let portal = TestPortal::with(Config::default(), |_| {
+        Background::with_autostart_dir(Arc::<Scripted<BackgroundUi>>::default(), autostart.clone())
+    });
+    let commandline = ["chat", "--background", "a b", "100%"];
+    assert!(enable_autostart(
+        &portal,
+        "org.example.Chat",
+        true,
+        &commandline
+    ));
+    let path = autostart.join("org.example.Chat.desktop");
+    let expected = "\
+[Desktop Entry]
+Type=Application
+Name=org.example.Chat
+Exec=flatpak run --command=chat org.example.Chat --background \"a b\" 100%%
+DBusActivatable=true
+X-Flatpak=org.example.Chat
+";
+    assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+    assert!(!enable_autostart(&portal, "org.example.Chat", false, &[]));
+    assert!(!path.exists());
+    assert!(!enable_autostart(&portal, "../Chat", true, &commandline));
+    assert_eq!(fs::read_dir(&autostart).unwrap().count(), 0);
+}
+
+#[test]
+fn no_app_is_reported_as_active() {
+    let dir = tempfile::tempdir().unwrap();
+    let portal = TestPortal::with(Config::default(), |_| {
+        Background::with_autostart_dir(Arc::<Scripted<BackgroundUi>>::default(), dir.path().into())
+    });
+    let apps: HashMap<String, OwnedValue> = portal.call(BACKGROUND, "GetAppState", &()).unwrap();
+    assert!(apps.is_empty());
+}
@@ -0,0 +1,112 @@
+use {
+    crate::{
+        config::Config,
+        gui::{dynamic_launcher::LauncherUi, DialogError},
+        portal::{
+            portals::dynamic_launcher::DynamicLauncher,
+            tests::{
+                backends::Scripted,
+                harness::{handle, Options, Response, TestPortal, CANCELLED, OTHER, SUCCESS},
+            },
+        },
+        utils::icon::Icon,
+    },
+    std::sync::Arc,
+    zbus::zvariant::{OwnedValue, Structure, Value},
+};
+
+const DYNAMIC_LAUNCHER: &str = "org.freedesktop.impl.portal.DynamicLauncher";
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn bytes_icon() -> Value<'static> {
+    Value::new(Structure::from((
+        "bytes".to_string(),
+        Value::new(PNG.to_vec()),
+    )))
+}
+
+fn prepare_install(portal: &TestPortal, options: Options) -> Response {
+    portal
+        .call(
+            DYNAMIC_LAUNCHER,
+            "PrepareInstall",
+            &(
+                handle(),
+                "org.example.Browser",
+                "",
+                "Example",
+                bytes_icon(),
+                options,
+            ),
+        )
+        .unwrap()
+}
+
+#[test]
+fn confirmed_launchers_return_the_chosen_name() {
+    let backend = Arc::new(Scripted::<LauncherUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        DynamicLauncher::new(backend.clone(), p.config())
+    });
+    backend.push(Ok("Renamed".to_string()));
+    let options = Options::from([
+        ("launcher_type", Value::new(2u32)),
+        ("target", Value::new("https://example.org")),
+        ("editable_name", Value::new(false)),
+    ]);
+    let (response, results) = prepare_install(&portal, options);
+    assert_eq!(response, SUCCESS);
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(ui.app_id, "org.example.Browser");
+    assert_eq!(ui.name, "Example");
+    assert_eq!(ui.icon, Some(Icon::Bytes(PNG.to_vec())));
+    assert_eq!(ui.target.as_deref(), Some("https://example.org"));
+    assert!(!ui.editable_name);
+    assert_eq!(<&str>::try_from(&results["name"]).unwrap(), "Renamed");
+    let icon = OwnedValue::try_from(bytes_icon()).unwrap();
+    assert_eq!(Value::try_from(&results["icon"]).unwrap(), Value::new(icon));
+}
+
+#[test]
+fn rejected_launchers_are_cancelled() {
+    let backend = Arc::new(Scripted::<LauncherUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        DynamicLauncher::new(backend.clone(), p.config())
+    });
+    backend.push(Err(DialogError::Rejected));
+    let (response, results) = prepare_install(&portal, Options::new());
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(ui.target, None);
+    assert!(ui.editable_name);
+}
+
+#[test]
+fn install_tokens_are_granted_to_configured_apps() {
+    let mut config = Config::default();
+    config.dynamic_launcher.install_token_apps = vec!["org.example.Games".to_string()];
+    let portal = TestPortal::with(config, |p| {
+        DynamicLauncher::new(Arc::<Scripted<LauncherUi>>::default(), p.config())
+    });
+    let request = |app_id: &str| -> u32 {
+        portal
+            .call(
+                DYNAMIC_LAUNCHER,
+                "RequestInstallToken",
+                &(app_id, Options::new()),
+            )
+            .unwrap()
+    };
+    assert_eq!(request("org.example.Games"), SUCCESS);
+    assert_eq!(request("org.example.Other"), OTHER);
+}
+
+#[test]
+fn supported_launcher_types_are_published() {
+    let portal = TestPortal::new();
+    let proxy = portal.proxy(DYNAMIC_LAUNCHER);
+    let types: u32 = proxy.get_property("SupportedLauncherTypes").unwrap();
+    assert_eq!(types, 3);
+}
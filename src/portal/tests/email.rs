@@ -0,0 +1,130 @@
+use {
+    crate::{
+        config::Config,
+        portal::{
+            portals::email::Email,
+            tests::{
+                backends::FakeMailClient,
+                harness::{handle, Options, Response, TestPortal, OTHER, SUCCESS},
+            },
+        },
+    },
+    std::{
+        ffi::OsString,
+        fs::{self, File},
+        os::fd::OwnedFd,
+        sync::{atomic::Ordering::Relaxed, Arc},
+    },
+    zbus::zvariant::{Fd, Value},
+};
+
+const EMAIL: &str = "org.freedesktop.impl.portal.Email";
+
+/// Creates a portal that composes emails with `client`.
+fn compose_email(portal: &TestPortal, options: Options) -> Response {
+    portal
+        .call(
+            EMAIL,
+            "ComposeEmail",
+            &(handle(), "org.example.App", "", options),
+        )
+        .unwrap()
+}
+
+#[test]
+fn compose_email_builds_a_mailto_uri() {
+    let client = Arc::new(FakeMailClient::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Email::new(client.clone(), p.config())
+    });
+    let mut options = Options::new();
+    options.insert("address", Value::new("alice@example.org"));
+    options.insert("addresses", Value::new(vec!["bob@example.org"]));
+    options.insert(
+        "cc",
+        Value::new(vec!["carol@example.org", "dan@example.org"]),
+    );
+    options.insert("bcc", Value::new(vec!["eve@example.org"]));
+    options.insert("subject", Value::new("Q3 report & notes"));
+    options.insert("body", Value::new("See attached.\nThanks"));
+    options.insert("activation_token", Value::new("token"));
+    let (response, results) = compose_email(&portal, options);
+    assert_eq!(response, SUCCESS);
+    assert!(results.is_empty());
+    let draft = client.drafts.lock().unwrap().pop().unwrap();
+    assert_eq!(
+        draft.uri,
+        "mailto:alice@example.org,bob@example.org\
+         ?cc=carol@example.org&cc=dan@example.org&bcc=eve@example.org\
+         &subject=Q3%20report%20%26%20notes&body=See%20attached.%0AThanks",
+    );
+    assert_eq!(draft.command, None);
+    assert_eq!(draft.activation_token.as_deref(), Some("token"));
+}
+
+#[test]
+fn attachments_are_passed_as_paths() {
+    let client = Arc::new(FakeMailClient::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Email::new(client.clone(), p.config())
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("report 1.pdf");
+    fs::write(&path, "report").unwrap();
+    let file = File::open(&path).unwrap();
+    let mut options = Options::new();
+    options.insert(
+        "attachment_fds",
+        Value::new(vec![Fd::from(OwnedFd::from(file))]),
+    );
+    let (response, _) = compose_email(&portal, options);
+    assert_eq!(response, SUCCESS);
+    let draft = client.drafts.lock().unwrap().pop().unwrap();
+    let expected = format!(
+        "mailto:?attachment={}/report%201.pdf",
+        dir.path().canonicalize().unwrap().display(),
+    );
+    assert_eq!(draft.uri, expected);
+}
+
+#[test]
+fn deleted_attachments_are_rejected() {
+    let client = Arc::new(FakeMailClient::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Email::new(client.clone(), p.config())
+    });
+    let file = tempfile::tempfile().unwrap();
+    let mut options = Options::new();
+    options.insert(
+        "attachment_fds",
+        Value::new(vec![Fd::from(OwnedFd::from(file))]),
+    );
+    let (response, _) = compose_email(&portal, options);
+    assert_eq!(response, OTHER);
+    assert!(client.drafts.lock().unwrap().is_empty());
+}
+
+#[test]
+fn configured_command_is_used() {
+    let client = Arc::new(FakeMailClient::default());
+    let mut config = Config::default();
+    config.email.command = Some(vec!["thunderbird".into(), "-compose".into()]);
+    let portal = TestPortal::with(config, |p| Email::new(client.clone(), p.config()));
+    let (response, _) = compose_email(&portal, Options::new());
+    assert_eq!(response, SUCCESS);
+    let draft = client.drafts.lock().unwrap().pop().unwrap();
+    assert_eq!(draft.uri, "mailto:");
+    let expected: Vec<OsString> = vec!["thunderbird".into(), "-compose".into()];
+    assert_eq!(draft.command, Some(expected));
+}
+
+#[test]
+fn launch_errors_are_reported() {
+    let client = Arc::new(FakeMailClient::default());
+    client.fail.store(true, Relaxed);
+    let portal = TestPortal::with(Config::default(), |p| {
+        Email::new(client.clone(), p.config())
+    });
+    let (response, _) = compose_email(&portal, Options::new());
+    assert_eq!(response, OTHER);
+}
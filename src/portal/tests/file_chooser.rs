@@ -0,0 +1,417 @@
+use {
+    crate::{
+        config::Config,
+        config::{app_rules::AppRules, Config},
+        gui::file_chooser::{
+            Choice, ChoiceVariant, FileChooserError, FileChooserResult, Filter, FilterKind,
+            FinalChoice,
+        },
+        portal::{
+            portals::file_chooser::FileChooser,
+            tests::{
+                backends::ScriptedFileChooser,
+                harness::{
+                    file_path, handle, Options, Response, TestPortal, CANCELLED, OTHER, SUCCESS,
+                },
+            },
+        },
+    },
+    serde::de::DeserializeOwned,
+    std::{fs, sync::Arc},
+    url::Url,
+    zbus::zvariant::{DeserializeDict, Type, Value},
+};
+
+const FILE_CHOOSER: &str = "org.freedesktop.impl.portal.FileChooser";
+
+type FileFilter = (String, Vec<(u32, String)>);
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct OpenFileResults {
+    uris: Option<Vec<String>>,
+    choices: Option<Vec<(String, String)>>,
+    current_filter: Option<FileFilter>,
+    writable: Option<bool>,
+}
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct SaveFilesResults {
+    uris: Option<Vec<String>>,
+}
+
+fn save_files<R>(portal: &TestPortal, files: &[&str]) -> (u32, R)
+where
+    R: DeserializeOwned + Type,
+{
+    let mut options = Options::new();
+    let files: Vec<_> = files.iter().map(|f| file_path(f)).collect();
+    options.insert("files", Value::new(files));
+    portal
+        .call(
+            FILE_CHOOSER,
+            "SaveFiles",
+            &(handle(), "org.example.App", "", "Save", options),
+        )
+        .unwrap()
+}
+
+fn selected(uris: &[&str]) -> Result<FileChooserResult, FileChooserError> {
+    Ok(FileChooserResult {
+        uris: uris.iter().map(|u| u.to_string()).collect(),
+        current_filter: None,
+        final_choices: None,
+        writeable: false,
+    })
+}
+
+fn text_filter() -> FileFilter {
+    (
+        "Text".to_string(),
+        vec![(0, "*.txt".to_string()), (1, "text/plain".to_string())],
+    )
+}
+
+fn image_filter() -> FileFilter {
+    ("Images".to_string(), vec![(1, "image/*".to_string())])
+}
+
+/// `Close` may arrive while the dialog is still being set up. The request object must
+/// already exist at that point and the close must reach the dialog.
+#[test]
+fn close_right_after_open_file_cancels_it() {
+    let backend = Arc::new(ScriptedFileChooser::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        FileChooser::new(backend.clone(), p.config())
+    });
+    let handle = handle();
+    let mut options = Options::new();
+    options.insert("multiple", Value::new(true));
+    options.insert("current_folder", Value::new(file_path("/tmp")));
+    let pending = portal.spawn_call::<_, Response>(
+        FILE_CHOOSER,
+        "OpenFile",
+        (handle.clone(), "org.example.App", "", "Open", options),
+    );
+    portal.close(&handle);
+    let (response, results) = pending.join().unwrap().unwrap();
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+    assert!(!portal.request_exists(&handle));
+    let dialogs = backend.dialogs();
+    assert_eq!(dialogs.len(), 1);
+    assert!(dialogs[0].multiple);
+}
+
+#[test]
+fn close_cancels_save_file() {
+    let portal = TestPortal::new();
+    let handle = handle();
+    let mut options = Options::new();
+    options.insert("current_name", Value::new("file.txt"));
+    options.insert("filters", Value::new(vec![("Text", vec![(0u32, "*.txt")])]));
+    let pending = portal.spawn_call::<_, Response>(
+        FILE_CHOOSER,
+        "SaveFile",
+        (handle.clone(), "org.example.App", "", "Save", options),
+    );
+    portal.close(&handle);
+    let (response, _) = pending.join().unwrap().unwrap();
+    assert_eq!(response, CANCELLED);
+    assert!(!portal.request_exists(&handle));
+}
+
+#[test]
+fn save_files_rejects_unsafe_paths() {
+    let portal = TestPortal::new();
+    for file in ["/etc/passwd", "a/b", ".", ".."] {
+        let (response, results): Response = save_files(&portal, &["ok.txt", file]);
+        assert_eq!(response, OTHER, "{file}");
+        assert!(results.is_empty());
+    }
+}
+
+#[test]
+fn open_file_maps_options_and_results() {
+    let backend = Arc::new(ScriptedFileChooser::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        FileChooser::new(backend.clone(), p.config())
+    });
+    backend.push(Ok(FileChooserResult {
+        uris: vec!["file:///tmp/a.txt".to_string()],
+        current_filter: Some(Filter {
+            name: "Text".to_string(),
+            elements: vec![
+                FilterKind::Glob("*.txt".to_string()),
+                FilterKind::Mime("text/plain".to_string()),
+            ],
+        }),
+        final_choices: Some(vec![FinalChoice {
+            id: "encoding".to_string(),
+            variant_id: "utf8".to_string(),
+        }]),
+        writeable: true,
+    }));
+    let mut filters = vec![text_filter()];
+    // unknown filter kinds are dropped
+    filters[0].1.push((7, "ignored".to_string()));
+    let choices = vec![(
+        "encoding",
+        "Encoding",
+        vec![("utf8", "UTF-8"), ("latin1", "Latin-1")],
+        "latin1",
+    )];
+    let mut options = Options::new();
+    options.insert("accept_label", Value::new("_Import"));
+    options.insert("modal", Value::new(false));
+    options.insert("multiple", Value::new(true));
+    options.insert("filters", Value::new(filters));
+    options.insert("current_filter", Value::new(text_filter()));
+    options.insert("choices", Value::new(choices));
+    options.insert("current_folder", Value::new(file_path("/tmp")));
+    let (response, results): (u32, OpenFileResults) = portal
+        .call(
+            FILE_CHOOSER,
+            "OpenFile",
+            &(handle(), "org.example.App", "x11:1", "Import", options),
+        )
+        .unwrap();
+
+    let ui = backend.dialogs().pop().unwrap();
+    let text = Filter {
+        name: "Text".to_string(),
+        elements: vec![
+            FilterKind::Glob("*.txt".to_string()),
+            FilterKind::Mime("text/plain".to_string()),
+        ],
+    };
+    assert_eq!(ui.title, "Import");
+    assert_eq!(ui.accept_label.as_deref(), Some("_Import"));
+    assert!(!ui.modal);
+    assert!(ui.multiple);
+    assert!(!ui.directory);
+    assert!(!ui.save);
+    assert_eq!(ui.parent_window, "x11:1");
+    assert_eq!(ui.app_id, "org.example.App");
+    assert_eq!(ui.filters, Some(vec![text.clone()]));
+    assert_eq!(ui.current_filter, Some(text));
+    assert_eq!(ui.current_folder.as_deref(), Some("/tmp"));
+    assert_eq!(
+        ui.choices,
+        Some(vec![Choice {
+            id: "encoding".to_string(),
+            label: "Encoding".to_string(),
+            default: "latin1".to_string(),
+            variants: vec![
+                ChoiceVariant {
+                    id: "utf8".to_string(),
+                    label: "UTF-8".to_string(),
+                },
+                ChoiceVariant {
+                    id: "latin1".to_string(),
+                    label: "Latin-1".to_string(),
+                },
+            ],
+        }]),
+    );
+
+    assert_eq!(response, SUCCESS);
+    assert_eq!(results.uris, Some(vec!["file:///tmp/a.txt".to_string()]));
+    assert_eq!(
+        results.choices,
+        Some(vec![("encoding".to_string(), "utf8".to_string())]),
+    );
+    assert_eq!(results.current_filter, Some(text_filter()));
+    assert_eq!(results.writable, Some(true));
+}
+
+#[test]
+fn ui_errors_are_mapped_to_responses() {
+    let backend = Arc::new(ScriptedFileChooser::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        FileChooser::new(backend.clone(), p.config())
+    });
+    for (error, expected) in [
+        (FileChooserError::Rejected, CANCELLED),
+        (FileChooserError::Cancelled, CANCELLED),
+        (FileChooserError::Closed, OTHER),
+    ] {
+        backend.push(Err(error));
+        let (response, _) = portal
+            .call::<_, Response>(
+                FILE_CHOOSER,
+                "OpenFile",
+                &(handle(), "org.example.App", "", "Open", Options::new()),
+            )
+            .unwrap();
+        assert_eq!(response, expected);
+    }
+}
+
+#[test]
+fn app_policy_overrides_options() {
+    let config = Config {
+        apps: AppRules::from_data(
+            "[org.example.*]\n\
+             current-folder=/srv/shared\n\
+             read-only=true\n\
+             filters=Images\n",
+        ),
+        ..Config::default()
+    };
+    let backend = Arc::new(ScriptedFileChooser::default());
+    let portal = TestPortal::with(config, |p| FileChooser::new(backend.clone(), p.config()));
+    backend.push(selected(&[]));
+    let mut options = Options::new();
+    options.insert("filters", Value::new(vec![text_filter(), image_filter()]));
+    options.insert("current_filter", Value::new(text_filter()));
+    options.insert("current_folder", Value::new(file_path("/home")));
+    let (response, _) = portal
+        .call::<_, Response>(
+            FILE_CHOOSER,
+            "OpenFile",
+            &(handle(), "org.example.App", "", "Open", options),
+        )
+        .unwrap();
+    assert_eq!(response, SUCCESS);
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(ui.current_folder.as_deref(), Some("/srv/shared"));
+    assert_eq!(ui.policy.read_only, Some(true));
+    let filters: Vec<_> = ui.filters.unwrap().into_iter().map(|f| f.name).collect();
+    assert_eq!(filters, ["Images"]);
+    assert_eq!(ui.current_filter, None);
+}
+
+#[test]
+fn app_policy_without_allowed_filters_fails() {
+    let config = Config {
+        apps: AppRules::from_data("[org.example.App]\nfilters=Images\n"),
+        ..Config::default()
+    };
+    let backend = Arc::new(ScriptedFileChooser::default());
+    let portal = TestPortal::with(config, |p| FileChooser::new(backend.clone(), p.config()));
+    let call = |method: &'static str, options: Options| {
+        portal
+            .call::<_, Response>(
+                FILE_CHOOSER,
+                method,
+                &(handle(), "org.example.App", "", "Files", options),
+            )
+            .unwrap()
+    };
+    let mut options = Options::new();
+    options.insert("filters", Value::new(vec![text_filter()]));
+    let (response, _) = call("OpenFile", options);
+    assert_eq!(response, OTHER);
+    let (response, _) = call("SaveFile", Options::new());
+    assert_eq!(response, OTHER);
+    assert!(backend.dialogs().is_empty());
+}
+
+#[test]
+fn app_policy_folder_replaces_current_filename() {
+    let config = Config {
+        apps: AppRules::from_data("[org.example.App]\ncurrent-folder=/srv/shared\n"),
+        ..Config::default()
+    };
+    let backend = Arc::new(ScriptedFileChooser::default());
+    let portal = TestPortal::with(config, |p| FileChooser::new(backend.clone(), p.config()));
+    backend.push(selected(&["file:///srv/shared/report.pdf"]));
+    let mut options = Options::new();
+    options.insert(
+        "current_file",
+        Value::new(file_path("/home/user/report.pdf")),
+    );
+    let (response, _) = portal
+        .call::<_, Response>(
+            FILE_CHOOSER,
+            "SaveFile",
+            &(handle(), "org.example.App", "", "Save", options),
+        )
+        .unwrap();
+    assert_eq!(response, SUCCESS);
+    let ui = backend.dialogs().pop().unwrap();
+    assert!(ui.save);
+    assert_eq!(ui.current_folder.as_deref(), Some("/srv/shared"));
+    assert_eq!(ui.current_filename, None);
+    assert_eq!(ui.current_name.as_deref(), Some("report.pdf"));
+}
+
+#[test]
+fn current_file_reaches_the_dialog_as_a_path() {
+    let backend = Arc::new(ScriptedFileChooser::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        FileChooser::new(backend.clone(), p.config())
+    });
+    backend.push(selected(&[]));
+    let mut options = Options::new();
+    options.insert(
+        "current_file",
+        Value::new(file_path("/home/user/My Report.pdf")),
+    );
+    let (response, _) = portal
+        .call::<_, Response>(
+            FILE_CHOOSER,
+            "SaveFile",
+            &(handle(), "org.example.App", "", "Save", options),
+        )
+        .unwrap();
+    assert_eq!(response, SUCCESS);
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(
+        ui.current_filename.as_deref(),
+        Some("/home/user/My Report.pdf")
+    );
+}
+
+#[test]
+fn save_files_picks_unique_names() {
+    let dir = tempfile::tempdir().unwrap();
+    for file in ["a.txt", "a (1).txt", "b"] {
+        fs::write(dir.path().join(file), "").unwrap();
+    }
+    let backend = Arc::new(ScriptedFileChooser::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        FileChooser::new(backend.clone(), p.config())
+    });
+    let dir_uri = Url::from_directory_path(dir.path()).unwrap();
+    backend.push(selected(&[dir_uri.as_str()]));
+    let (response, results): (u32, SaveFilesResults) =
+        save_files(&portal, &["a.txt", "b", "c.tar.gz"]);
+    assert_eq!(response, SUCCESS);
+    let expected: Vec<_> = ["a (2).txt", "b (1)", "c.tar.gz"]
+        .iter()
+        .map(|f| Url::from_file_path(dir.path().join(f)).unwrap().to_string())
+        .collect();
+    assert_eq!(results.uris, Some(expected));
+    let ui = backend.dialogs().pop().unwrap();
+    assert!(ui.directory);
+    assert!(ui.save);
+}
+
+#[test]
+fn save_files_requires_exactly_one_folder() {
+    let backend = Arc::new(ScriptedFileChooser::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        FileChooser::new(backend.clone(), p.config())
+    });
+    backend.push(selected(&[]));
+    backend.push(selected(&["file:///tmp", "file:///srv"]));
+    backend.push(selected(&["https://example.org/"]));
+    for _ in 0..3 {
+        let (response, _): Response = save_files(&portal, &["a.txt"]);
+        assert_eq!(response, OTHER);
+    }
+}
+
+#[test]
+fn unsafe_paths_are_rejected_before_showing_a_dialog() {
+    let backend = Arc::new(ScriptedFileChooser::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        FileChooser::new(backend.clone(), p.config())
+    });
+    let (response, _): Response = save_files(&portal, &["../a.txt"]);
+    assert_eq!(response, OTHER);
+    assert!(backend.dialogs().is_empty());
+}
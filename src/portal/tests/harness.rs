@@ -0,0 +1,357 @@
+use {
+    crate::{
+        config::{Config, ConfigHandle},
+        gui::{settings::Namespaces, UiProxy},
+        portal::{add_interfaces, portals::settings, PATH},
+    },
+    async_channel::Receiver,
+    serde::{de::DeserializeOwned, Serialize},
+    std::{
+        collections::HashMap,
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+        sync::atomic::{AtomicU64, Ordering::Relaxed},
+        thread::{self, JoinHandle},
+        time::{Duration, Instant},
+    },
+    zbus::{
+        blocking::{connection, Connection, Proxy},
+        names::OwnedUniqueName,
+        object_server::Interface,
+        zvariant::{DynamicType, OwnedObjectPath, OwnedValue, Type, Value},
+    },
+};
+
+pub type Options = HashMap<&'static str, Value<'static>>;
+
+pub type Results = HashMap<String, OwnedValue>;
+
+pub type Response = (u32, Results);
+
+pub const SUCCESS: u32 = 0;
+pub const CANCELLED: u32 = 1;
+pub const OTHER: u32 = 2;
+
+const REQUEST: &str = "org.freedesktop.impl.portal.Request";
+const SESSION: &str = "org.freedesktop.impl.portal.Session";
+const UNKNOWN_OBJECT: &str = "org.freedesktop.DBus.Error.UnknownObject";
+
+/// A private session bus that is shut down when this object is dropped.
+pub struct TestBus {
+    daemon: Child,
+    address: String,
+}
+
+impl TestBus {
+    pub fn new() -> Self {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("could not spawn dbus-daemon");
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .expect("could not read the bus address");
+        Self {
+            daemon,
+            address: address.trim().to_string(),
+        }
+    }
+
+    pub fn connect(&self) -> Connection {
+        connection::Builder::address(&*self.address)
+            .unwrap()
+            .build()
+            .expect("could not connect to the test bus")
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+/// The portal served on a private bus and a client connected to the same bus.
+///
+/// Dialogs are never shown. Requests that would show a dialog only complete when they
+/// are closed.
+pub struct TestPortal {
+    client: Connection,
+    portal: Connection,
+    config: ConfigHandle,
+    bus: TestBus,
+}
+
+impl TestPortal {
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Creates a portal with the configuration `config` whose interface of type `I` is
+    /// replaced by the one returned by `f`, e.g. to use a scripted backend.
+    pub fn with<I, F>(config: Config, f: F) -> Self
+    where
+        I: Interface,
+        F: FnOnce(&TestPortal) -> I,
+    {
+        Self::builder().config(config).interface(f).build()
+    }
+
+    pub fn builder<'a>() -> TestPortalBuilder<'a> {
+        TestPortalBuilder {
+            config: ConfigHandle::fixed(Config::default()),
+            setup: vec![],
+        }
+    }
+
+    /// Returns the configuration of the portal.
+    pub fn config(&self) -> &ConfigHandle {
+        &self.config
+    }
+
+    /// Opens another connection to the bus, e.g. for a service that the portal uses.
+    pub fn connect(&self) -> Connection {
+        self.bus.connect()
+    }
+
+    /// Returns the unique name of the portal.
+    pub fn name(&self) -> OwnedUniqueName {
+        self.portal.unique_name().unwrap().to_owned()
+    }
+
+    /// Returns a proxy for an interface of the portal.
+    pub fn proxy(&self, interface: &'static str) -> Proxy<'static> {
+        Proxy::new(
+            &self.client,
+            self.portal.unique_name().unwrap().to_owned(),
+            PATH,
+            interface,
+        )
+        .unwrap()
+    }
+
+    /// Adds an interface to the portal object.
+    pub fn serve<I: Interface>(&self, interface: I) {
+        self.portal.object_server().at(PATH, interface).unwrap();
+    }
+
+    /// Replaces the interface of the same type.
+    fn replace<I: Interface>(&self, interface: I) {
+        let server = self.portal.object_server();
+        server.remove::<I, _>(PATH).unwrap();
+        server.at(PATH, interface).unwrap();
+    }
+
+    /// Calls a method of the portal and waits for the reply.
+    pub fn call<B, R>(&self, interface: &str, method: &str, body: &B) -> zbus::Result<R>
+    where
+        B: Serialize + DynamicType,
+        R: DeserializeOwned + Type,
+    {
+        call(&self.client, &self.portal, interface, method, body)
+    }
+
+    /// Calls a method of the portal on another thread.
+    pub fn spawn_call<B, R>(
+        &self,
+        interface: &'static str,
+        method: &'static str,
+        body: B,
+    ) -> JoinHandle<zbus::Result<R>>
+    where
+        B: Serialize + DynamicType + Send + 'static,
+        R: DeserializeOwned + Type + Send + 'static,
+    {
+        let client = self.client.clone();
+        let portal = self.portal.clone();
+        thread::spawn(move || call(&client, &portal, interface, method, &body))
+    }
+
+    /// Closes a request.
+    ///
+    /// Since the request object is exported asynchronously, this retries for a short
+    /// while if the object does not exist yet.
+    pub fn close(&self, handle: &OwnedObjectPath) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            match self.try_close(handle) {
+                Ok(()) => return,
+                Err(zbus::Error::MethodError(name, _, _))
+                    if name.as_str() == UNKNOWN_OBJECT && Instant::now() < deadline =>
+                {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => panic!("could not close {}: {e}", handle.as_str()),
+            }
+        }
+    }
+
+    /// Waits until the request object has been exported.
+    pub fn wait_for_request(&self, handle: &OwnedObjectPath) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !self.request_exists(handle) {
+            assert!(
+                Instant::now() < deadline,
+                "{} was not exported",
+                handle.as_str()
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Returns whether the request object is currently exported.
+    ///
+    /// Unlike closing the request, this does not affect the request.
+    pub fn request_exists(&self, handle: &OwnedObjectPath) -> bool {
+        self.object_exists(&self.portal, handle)
+    }
+
+    /// Returns whether `owner` currently exports an object at `path`.
+    pub fn object_exists(&self, owner: &Connection, path: &OwnedObjectPath) -> bool {
+        let res = self.client.call_method(
+            owner.unique_name(),
+            path,
+            Some("org.freedesktop.DBus.Introspectable"),
+            "Introspect",
+            &(),
+        );
+        match res {
+            Ok(_) => true,
+            Err(zbus::Error::MethodError(name, _, _)) if name.as_str() == UNKNOWN_OBJECT => false,
+            Err(e) => panic!("could not introspect {}: {e}", path.as_str()),
+        }
+    }
+
+    /// Closes a session.
+    pub fn close_session(&self, handle: &OwnedObjectPath) -> zbus::Result<()> {
+        self.client
+            .call_method(
+                self.portal.unique_name(),
+                handle,
+                Some(SESSION),
+                "Close",
+                &(),
+            )
+            .map(drop)
+    }
+
+    /// Closes the request that `owner` exports at `handle`.
+    ///
+    /// Unlike [`TestPortal::close`], this fails if the request object does not exist yet.
+    pub fn close_request(&self, owner: &Connection, handle: &OwnedObjectPath) -> zbus::Result<()> {
+        self.client
+            .call_method(owner.unique_name(), handle, Some(REQUEST), "Close", &())
+            .map(drop)
+    }
+
+    fn try_close(&self, handle: &OwnedObjectPath) -> zbus::Result<()> {
+        self.close_request(&self.portal, handle)
+    }
+}
+
+/// Builds a [`TestPortal`] whose interfaces can be replaced, e.g. to use scripted
+/// backends.
+pub struct TestPortalBuilder<'a> {
+    config: ConfigHandle,
+    setup: Vec<Box<dyn FnOnce(&TestPortal) + 'a>>,
+}
+
+impl<'a> TestPortalBuilder<'a> {
+    pub fn config(self, config: Config) -> Self {
+        self.config_handle(ConfigHandle::fixed(config))
+    }
+
+    pub fn config_handle(mut self, config: ConfigHandle) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Replaces the interface of the same type with the one returned by `f`.
+    pub fn interface<I, F>(mut self, f: F) -> Self
+    where
+        I: Interface,
+        F: FnOnce(&TestPortal) -> I + 'a,
+    {
+        self.setup.push(Box::new(move |portal: &TestPortal| {
+            portal.replace(f(portal))
+        }));
+        self
+    }
+
+    /// Serves the settings that are sent through `updates` in place of those of GTK.
+    pub fn settings(mut self, updates: Receiver<Namespaces>) -> Self {
+        self.setup.push(Box::new(move |portal: &TestPortal| {
+            portal
+                .portal
+                .object_server()
+                .remove::<settings::Settings, _>(PATH)
+                .unwrap();
+            settings::serve(&portal.portal, updates).unwrap();
+        }));
+        self
+    }
+
+    pub fn build(self) -> TestPortal {
+        let bus = TestBus::new();
+        let portal = bus.connect();
+        add_interfaces(&portal, &UiProxy::detached(), &self.config)
+            .expect("could not add the portal interfaces");
+        let slf = TestPortal {
+            client: bus.connect(),
+            portal,
+            config: self.config,
+            bus,
+        };
+        for setup in self.setup {
+            setup(&slf);
+        }
+        slf
+    }
+}
+
+fn call<B, R>(
+    client: &Connection,
+    portal: &Connection,
+    interface: &str,
+    method: &str,
+    body: &B,
+) -> zbus::Result<R>
+where
+    B: Serialize + DynamicType,
+    R: DeserializeOwned + Type,
+{
+    client
+        .call_method(portal.unique_name(), PATH, Some(interface), method, body)?
+        .body()
+        .deserialize()
+}
+
+/// Returns a request handle that has not been used before.
+pub fn handle() -> OwnedObjectPath {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    let path = format!(
+        "/org/freedesktop/portal/desktop/request/1_1/t{}",
+        NEXT.fetch_add(1, Relaxed),
+    );
+    OwnedObjectPath::try_from(path).unwrap()
+}
+
+/// Returns a session handle that has not been used before.
+pub fn session_handle() -> OwnedObjectPath {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    let path = format!(
+        "/org/freedesktop/portal/desktop/session/1_1/t{}",
+        NEXT.fetch_add(1, Relaxed),
+    );
+    OwnedObjectPath::try_from(path).unwrap()
+}
+
+/// Encodes a path as a nul-terminated byte string.
+pub fn file_path(path: &str) -> Vec<u8> {
+    let mut bytes = path.as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+}
@@ -0,0 +1,226 @@
+use {
+    crate::portal::{
+        portals::inhibit::Inhibit,
+        tests::harness::{handle, session_handle, Options, Results, TestPortal, SUCCESS},
+    },
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    },
+    zbus::{
+        blocking::{Connection, Proxy, SignalIterator},
+        interface,
+        zvariant::{OwnedFd, OwnedObjectPath, Value},
+    },
+};
+
+const INHIBIT: &str = "org.freedesktop.impl.portal.Inhibit";
+const SESSION: &str = "org.freedesktop.impl.portal.Session";
+const LOGIND_NAME: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+const LOGIND_INTERFACE: &str = "org.freedesktop.login1.Manager";
+const SCREENSAVER_NAME: &str = "org.freedesktop.ScreenSaver";
+const SCREENSAVER_PATH: &str = "/org/freedesktop/ScreenSaver";
+
+const LOGOUT: u32 = 1;
+const USER_SWITCH: u32 = 2;
+const IDLE: u32 = 8;
+
+#[derive(Default)]
+struct Calls {
+    /// The `what`, `who`, `why` and `mode` of the logind inhibitors.
+    logind: Vec<(String, String, String, String)>,
+    screensaver: Vec<(String, String)>,
+    uninhibited: Vec<u32>,
+}
+
+struct StubLogind {
+    calls: Arc<Mutex<Calls>>,
+}
+
+#[interface(name = "org.freedesktop.login1.Manager")]
+impl StubLogind {
+    fn inhibit(&self, what: String, who: String, why: String, mode: String) -> OwnedFd {
+        self.calls
+            .lock()
+            .unwrap()
+            .logind
+            .push((what, who, why, mode));
+        let file = tempfile::tempfile().unwrap();
+        std::os::fd::OwnedFd::from(file).into()
+    }
+}
+
+struct StubScreenSaver {
+    calls: Arc<Mutex<Calls>>,
+}
+
+#[interface(name = "org.freedesktop.ScreenSaver")]
+impl StubScreenSaver {
+    fn inhibit(&self, app: String, reason: String) -> u32 {
+        let mut calls = self.calls.lock().unwrap();
+        calls.screensaver.push((app, reason));
+        calls.screensaver.len() as u32 + 10
+    }
+
+    fn un_inhibit(&self, cookie: u32) {
+        self.calls.lock().unwrap().uninhibited.push(cookie);
+    }
+
+    fn get_active(&self) -> bool {
+        false
+    }
+}
+
+struct Setup {
+    portal: TestPortal,
+    services: Connection,
+    calls: Arc<Mutex<Calls>>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        // the test bus is used in place of the system bus
+        let portal = TestPortal::builder()
+            .interface(|p| Inhibit::with_system_bus(p.connect().inner().clone()))
+            .build();
+        let services = portal.connect();
+        let calls = Arc::new(Mutex::new(Calls::default()));
+        let logind = StubLogind {
+            calls: calls.clone(),
+        };
+        services.object_server().at(LOGIND_PATH, logind).unwrap();
+        let screensaver = StubScreenSaver {
+            calls: calls.clone(),
+        };
+        services
+            .object_server()
+            .at(SCREENSAVER_PATH, screensaver)
+            .unwrap();
+        services.request_name(LOGIND_NAME).unwrap();
+        services.request_name(SCREENSAVER_NAME).unwrap();
+        Self {
+            portal,
+            services,
+            calls,
+        }
+    }
+
+    fn emit(&self, path: &str, interface: &str, signal: &str, value: bool) {
+        self.services
+            .emit_signal(None::<()>, path, interface, signal, &(value,))
+            .unwrap();
+    }
+}
+
+fn next_state(states: &mut SignalIterator<'_>) -> (OwnedObjectPath, Results) {
+    let msg = states.next().unwrap();
+    msg.body().deserialize().unwrap()
+}
+
+#[test]
+fn inhibitions_are_forwarded_until_closed() {
+    let setup = Setup::new();
+    let handle = handle();
+    let options: Options = HashMap::from([("reason", Value::new("Playing a video"))]);
+    setup
+        .portal
+        .call::<_, ()>(
+            INHIBIT,
+            "Inhibit",
+            &(
+                &handle,
+                "org.example.App",
+                "",
+                LOGOUT | USER_SWITCH | IDLE,
+                options,
+            ),
+        )
+        .unwrap();
+    {
+        let calls = setup.calls.lock().unwrap();
+        let logind = &calls.logind[0];
+        assert_eq!(logind.0, "shutdown:idle");
+        assert_eq!(logind.1, "org.example.App");
+        assert_eq!(logind.2, "Playing a video");
+        assert_eq!(logind.3, "block");
+        assert_eq!(
+            calls.screensaver,
+            [("org.example.App".to_string(), "Playing a video".to_string())]
+        );
+        assert!(calls.uninhibited.is_empty());
+    }
+    setup.portal.close(&handle);
+    assert_eq!(setup.calls.lock().unwrap().uninhibited, [11]);
+    assert!(!setup.portal.request_exists(&handle));
+}
+
+#[test]
+fn duplicate_inhibitions_are_released() {
+    let setup = Setup::new();
+    let handle = handle();
+    let inhibit = || {
+        setup.portal.call::<_, ()>(
+            INHIBIT,
+            "Inhibit",
+            &(&handle, "org.example.App", "", IDLE, Options::new()),
+        )
+    };
+    inhibit().unwrap();
+    assert!(inhibit().is_err());
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while setup.calls.lock().unwrap().uninhibited.is_empty() {
+        assert!(Instant::now() < deadline, "the inhibition was not released");
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(setup.calls.lock().unwrap().uninhibited, [12]);
+    setup.portal.close(&handle);
+    assert_eq!(setup.calls.lock().unwrap().uninhibited, [12, 11]);
+}
+
+#[test]
+fn monitors_report_state_changes() {
+    let setup = Setup::new();
+    let proxy = setup.portal.proxy(INHIBIT);
+    let mut states = proxy.receive_signal("StateChanged").unwrap();
+    let session = session_handle();
+    let response: u32 = setup
+        .portal
+        .call(
+            INHIBIT,
+            "CreateMonitor",
+            &(handle(), &session, "org.example.App", ""),
+        )
+        .unwrap();
+    assert_eq!(response, SUCCESS);
+    let (path, state) = next_state(&mut states);
+    assert_eq!(path, session);
+    assert!(!bool::try_from(&state["screensaver-active"]).unwrap());
+    assert_eq!(u32::try_from(&state["session-state"]).unwrap(), 1);
+    assert_eq!(setup.calls.lock().unwrap().logind[0].3, "delay");
+    let session_proxy = Proxy::new(
+        &setup.portal.connect(),
+        setup.portal.name(),
+        &session,
+        SESSION,
+    )
+    .unwrap();
+    assert_eq!(session_proxy.get_property::<u32>("version").unwrap(), 1);
+
+    setup.emit(SCREENSAVER_PATH, SCREENSAVER_NAME, "ActiveChanged", true);
+    let (_, state) = next_state(&mut states);
+    assert!(bool::try_from(&state["screensaver-active"]).unwrap());
+
+    setup.emit(LOGIND_PATH, LOGIND_INTERFACE, "PrepareForShutdown", true);
+    let (_, state) = next_state(&mut states);
+    assert_eq!(u32::try_from(&state["session-state"]).unwrap(), 2);
+    setup
+        .portal
+        .call::<_, ()>(INHIBIT, "QueryEndResponse", &(&session,))
+        .unwrap();
+
+    setup.portal.close_session(&session).unwrap();
+    assert!(setup.portal.close_session(&session).is_err());
+}
@@ -0,0 +1,67 @@
+use {
+    crate::{
+        config::{Config, ConfigHandle},
+        portal::{
+            portals::file_chooser::FileChooser,
+            tests::{
+                backends::ScriptedFileChooser,
+                harness::{file_path, handle, Options, Response, TestPortal, OTHER},
+            },
+        },
+    },
+    std::{fs, sync::Arc},
+    zbus::zvariant::Value,
+};
+
+const LOCKDOWN: &str = "org.freedesktop.impl.portal.Lockdown";
+const FILE_CHOOSER: &str = "org.freedesktop.impl.portal.FileChooser";
+
+#[test]
+fn properties_follow_the_configuration() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config");
+    fs::write(&path, "[lockdown]\ndisable-printing=true\n").unwrap();
+    let config = ConfigHandle::load(path.clone());
+    let portal = TestPortal::builder().config_handle(config.clone()).build();
+    let proxy = portal.proxy(LOCKDOWN);
+    assert!(proxy.get_property::<bool>("disable-printing").unwrap());
+    assert!(!proxy.get_property::<bool>("disable-camera").unwrap());
+    let mut changes = proxy.receive_property_changed::<bool>("disable-camera");
+    fs::write(
+        &path,
+        "[lockdown]\ndisable-printing=true\ndisable-camera=true\n",
+    )
+    .unwrap();
+    config.reload();
+    let change = changes.next().unwrap();
+    assert!(change.get().unwrap());
+    assert!(proxy.get_property::<bool>("disable-camera").unwrap());
+}
+
+#[test]
+fn save_to_disk_can_be_disabled() {
+    let backend = Arc::new(ScriptedFileChooser::default());
+    let mut config = Config::default();
+    config.lockdown.disable_save_to_disk = true;
+    let portal = TestPortal::with(config, |p| FileChooser::new(backend.clone(), p.config()));
+    let (response, results): Response = portal
+        .call(
+            FILE_CHOOSER,
+            "SaveFile",
+            &(handle(), "org.example.App", "", "Save", Options::new()),
+        )
+        .unwrap();
+    assert_eq!(response, OTHER);
+    assert!(results.is_empty());
+    let mut options = Options::new();
+    options.insert("files", Value::new(vec![file_path("report.pdf")]));
+    let (response, _): Response = portal
+        .call(
+            FILE_CHOOSER,
+            "SaveFiles",
+            &(handle(), "org.example.App", "", "Save", options),
+        )
+        .unwrap();
+    assert_eq!(response, OTHER);
+    assert!(backend.dialogs().is_empty());
+}
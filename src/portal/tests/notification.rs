@@ -0,0 +1,242 @@
+use {
+    crate::portal::tests::harness::TestPortal,
+    gtk4::gdk_pixbuf::{Colorspace, Pixbuf},
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    },
+    zbus::{
+        blocking::Connection,
+        interface,
+        zvariant::{OwnedValue, Structure, Value},
+    },
+};
+
+const NOTIFICATION: &str = "org.freedesktop.impl.portal.Notification";
+const SERVER_NAME: &str = "org.freedesktop.Notifications";
+const SERVER_PATH: &str = "/org/freedesktop/Notifications";
+
+#[derive(Debug)]
+struct Notify {
+    id: u32,
+    app_name: String,
+    replaces_id: u32,
+    app_icon: String,
+    summary: String,
+    body: String,
+    actions: Vec<String>,
+    hints: HashMap<String, OwnedValue>,
+    expire_timeout: i32,
+}
+
+#[derive(Default)]
+struct Calls {
+    notify: Vec<Notify>,
+    closed: Vec<u32>,
+}
+
+/// A notification server that records its calls.
+struct StubServer {
+    calls: Arc<Mutex<Calls>>,
+    next_id: u32,
+}
+
+#[interface(name = "org.freedesktop.Notifications")]
+impl StubServer {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &mut self,
+        app_name: String,
+        replaces_id: u32,
+        app_icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        expire_timeout: i32,
+    ) -> u32 {
+        self.next_id += 1;
+        self.calls.lock().unwrap().notify.push(Notify {
+            id: self.next_id,
+            app_name,
+            replaces_id,
+            app_icon,
+            summary,
+            body,
+            actions,
+            hints,
+            expire_timeout,
+        });
+        self.next_id
+    }
+
+    fn close_notification(&self, id: u32) {
+        self.calls.lock().unwrap().closed.push(id);
+    }
+}
+
+struct Setup {
+    portal: TestPortal,
+    server: Connection,
+    calls: Arc<Mutex<Calls>>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let portal = TestPortal::new();
+        let server = portal.connect();
+        let calls = Arc::new(Mutex::new(Calls::default()));
+        let stub = StubServer {
+            calls: calls.clone(),
+            next_id: 0,
+        };
+        server.object_server().at(SERVER_PATH, stub).unwrap();
+        server.request_name(SERVER_NAME).unwrap();
+        Self {
+            portal,
+            server,
+            calls,
+        }
+    }
+
+    fn add(&self, id: &str, notification: HashMap<&str, Value<'_>>) {
+        self.portal
+            .call::<_, ()>(
+                NOTIFICATION,
+                "AddNotification",
+                &("org.example.App", id, notification),
+            )
+            .unwrap();
+    }
+
+    fn emit(&self, signal: &str, body: &(u32, impl serde::Serialize + zbus::zvariant::Type)) {
+        self.server
+            .emit_signal(None::<()>, SERVER_PATH, SERVER_NAME, signal, body)
+            .unwrap();
+    }
+}
+
+fn themed_icon(name: &str) -> Value<'static> {
+    let names = Value::new(vec![name.to_string()]);
+    Value::new(Structure::from(("themed".to_string(), names)))
+}
+
+/// Returns a PNG icon of two opaque red pixels.
+fn bytes_icon() -> Value<'static> {
+    let pixbuf = Pixbuf::new(Colorspace::Rgb, true, 8, 2, 1).unwrap();
+    pixbuf.fill(0xff0000ff);
+    let png = pixbuf.save_to_bufferv("png", &[]).unwrap();
+    Value::new(Structure::from(("bytes".to_string(), Value::new(png))))
+}
+
+fn notification() -> HashMap<&'static str, Value<'static>> {
+    let button = HashMap::from([
+        ("label", Value::new("Reply")),
+        ("action", Value::new("app.reply")),
+        ("target", Value::new("thread-7")),
+    ]);
+    HashMap::from([
+        ("title", Value::new("New message")),
+        ("body", Value::new("Hi there")),
+        ("icon", themed_icon("mail-unread")),
+        ("priority", Value::new("urgent")),
+        ("default-action", Value::new("app.open")),
+        ("default-action-target", Value::new(3u32)),
+        ("buttons", Value::new(vec![button])),
+    ])
+}
+
+#[test]
+fn notifications_are_forwarded() {
+    let setup = Setup::new();
+    setup.add("msg", notification());
+    let calls = setup.calls.lock().unwrap();
+    let notify = &calls.notify[0];
+    assert_eq!(notify.app_name, "org.example.App");
+    assert_eq!(notify.replaces_id, 0);
+    assert_eq!(notify.app_icon, "mail-unread");
+    assert_eq!(notify.summary, "New message");
+    assert_eq!(notify.body, "Hi there");
+    assert_eq!(notify.actions, ["default", "", "button-0", "Reply"]);
+    assert_eq!(u8::try_from(&notify.hints["urgency"]).unwrap(), 2);
+    assert_eq!(
+        <&str>::try_from(&notify.hints["desktop-entry"]).unwrap(),
+        "org.example.App"
+    );
+    assert_eq!(notify.expire_timeout, 0);
+}
+
+#[test]
+fn bytes_icons_are_sent_as_image_data() {
+    let setup = Setup::new();
+    let mut notification = notification();
+    notification.insert("icon", bytes_icon());
+    setup.add("msg", notification);
+    let calls = setup.calls.lock().unwrap();
+    let notify = &calls.notify[0];
+    assert_eq!(notify.app_icon, "");
+    let data = Value::try_from(&notify.hints["image-data"]).unwrap();
+    let data = <(i32, i32, i32, bool, i32, i32, Vec<u8>)>::try_from(data).unwrap();
+    let red = [0xff, 0x00, 0x00, 0xff];
+    assert_eq!(data, (2, 1, 8, true, 8, 4, [red, red].concat()));
+}
+
+#[test]
+fn notifications_with_the_same_id_are_replaced() {
+    let setup = Setup::new();
+    setup.add("msg", notification());
+    setup.add("msg", HashMap::from([("title", Value::new("Updated"))]));
+    setup.add("other", HashMap::from([("title", Value::new("Other"))]));
+    let calls = setup.calls.lock().unwrap();
+    assert_eq!(calls.notify[1].replaces_id, calls.notify[0].id);
+    assert_eq!(calls.notify[1].actions, Vec::<String>::new());
+    assert_eq!(calls.notify[2].replaces_id, 0);
+}
+
+#[test]
+fn actions_are_mapped_back_to_the_app() {
+    let setup = Setup::new();
+    let proxy = setup.portal.proxy(NOTIFICATION);
+    let mut invoked = proxy.receive_signal("ActionInvoked").unwrap();
+    setup.add("msg", notification());
+    let server_id = setup.calls.lock().unwrap().notify[0].id;
+    setup.emit("ActionInvoked", &(server_id + 100, "default"));
+    setup.emit("ActionInvoked", &(server_id, "button-0"));
+    let msg = invoked.next().unwrap();
+    let (app_id, id, action, parameter): (String, String, String, Vec<OwnedValue>) =
+        msg.body().deserialize().unwrap();
+    assert_eq!((app_id.as_str(), id.as_str()), ("org.example.App", "msg"));
+    assert_eq!(action, "app.reply");
+    assert_eq!(parameter.len(), 1);
+    assert_eq!(<&str>::try_from(&parameter[0]).unwrap(), "thread-7");
+    setup.emit("ActionInvoked", &(server_id, "default"));
+    let msg = invoked.next().unwrap();
+    let (_, _, action, parameter): (String, String, String, Vec<OwnedValue>) =
+        msg.body().deserialize().unwrap();
+    assert_eq!(action, "app.open");
+    assert_eq!(u32::try_from(&parameter[0]).unwrap(), 3);
+}
+
+#[test]
+fn remove_notification_closes_it() {
+    let setup = Setup::new();
+    setup.add("msg", notification());
+    setup
+        .portal
+        .call::<_, ()>(
+            NOTIFICATION,
+            "RemoveNotification",
+            &("org.example.Other", "msg"),
+        )
+        .unwrap();
+    setup
+        .portal
+        .call::<_, ()>(
+            NOTIFICATION,
+            "RemoveNotification",
+            &("org.example.App", "msg"),
+        )
+        .unwrap();
+    let calls = setup.calls.lock().unwrap();
+    assert_eq!(calls.closed, [calls.notify[0].id]);
+}
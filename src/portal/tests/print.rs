@@ -0,0 +1,231 @@
+use {
+    crate::{
+        config::Config,
+        gui::print::{PageSetup, PrintError, PrintSetup},
+        portal::{
+            portals::print::Print,
+            tests::{
+                backends::PrintToFile,
+                harness::{handle, Options, Response, TestPortal, CANCELLED, SUCCESS},
+            },
+        },
+    },
+    std::{
+        collections::HashMap,
+        fs::{self, File},
+        io::Write,
+        path::Path,
+        sync::Arc,
+    },
+    url::Url,
+    zbus::zvariant::{DeserializeDict, Fd, OwnedValue, Type, Value},
+};
+
+const PRINT: &str = "org.freedesktop.impl.portal.Print";
+
+#[derive(DeserializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+struct PreparePrintResults {
+    settings: Option<HashMap<String, OwnedValue>>,
+    #[zvariant(rename = "page-setup")]
+    page_setup: Option<HashMap<String, OwnedValue>>,
+    token: Option<u32>,
+}
+
+fn print_to(path: &Path) -> PrintSetup {
+    let uri = Url::from_file_path(path).unwrap();
+    PrintSetup {
+        settings: HashMap::from([
+            ("printer".to_string(), "Print to File".to_string()),
+            ("output-uri".to_string(), uri.to_string()),
+        ]),
+        page_setup: PageSetup {
+            name: Some("iso_a4".to_string()),
+            width: Some(210.0),
+            height: Some(297.0),
+            orientation: Some("portrait".to_string()),
+            ..Default::default()
+        },
+    }
+}
+
+fn prepare_print(portal: &TestPortal, app_id: &str) -> (u32, PreparePrintResults) {
+    let settings = HashMap::from([("n-copies", Value::new("2"))]);
+    let page_setup = HashMap::from([("Name", Value::new("na_letter"))]);
+    portal
+        .call(
+            PRINT,
+            "PreparePrint",
+            &(
+                handle(),
+                app_id,
+                "",
+                "Report",
+                settings,
+                page_setup,
+                Options::new(),
+            ),
+        )
+        .unwrap()
+}
+
+fn print(portal: &TestPortal, app_id: &str, document: &Path, token: Option<u32>) -> Response {
+    let file = File::open(document).unwrap();
+    let mut options = Options::new();
+    if let Some(token) = token {
+        options.insert("token", Value::new(token));
+    }
+    portal
+        .call(
+            PRINT,
+            "Print",
+            &(handle(), app_id, "", "Report", Fd::from(&file), options),
+        )
+        .unwrap()
+}
+
+fn document(dir: &Path) -> std::path::PathBuf {
+    let path = dir.join("document.pdf");
+    File::create(&path).unwrap().write_all(b"%PDF-1.7").unwrap();
+    path
+}
+
+#[test]
+fn prepare_print_returns_the_selected_setup() {
+    let backend = Arc::new(PrintToFile::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Print::new(backend.clone(), p.config())
+    });
+    let dir = tempfile::tempdir().unwrap();
+    backend.push(Ok(print_to(&dir.path().join("out.pdf"))));
+    let (response, results) = prepare_print(&portal, "org.example.App");
+    assert_eq!(response, SUCCESS);
+    let settings = results.settings.unwrap();
+    assert_eq!(
+        <&str>::try_from(&settings["printer"]).unwrap(),
+        "Print to File"
+    );
+    let page_setup = results.page_setup.unwrap();
+    assert_eq!(<&str>::try_from(&page_setup["Name"]).unwrap(), "iso_a4");
+    assert_eq!(f64::try_from(&page_setup["Width"]).unwrap(), 210.0);
+    assert!(!page_setup.contains_key("PPDName"));
+    assert_ne!(results.token, Some(0));
+    assert!(results.token.is_some());
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(ui.title, "Report");
+    assert_eq!(ui.setup.settings["n-copies"], "2");
+    assert_eq!(ui.setup.page_setup.name.as_deref(), Some("na_letter"));
+}
+
+#[test]
+fn print_with_token_skips_the_dialog() {
+    let backend = Arc::new(PrintToFile::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Print::new(backend.clone(), p.config())
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("out.pdf");
+    backend.push(Ok(print_to(&output)));
+    let (_, results) = prepare_print(&portal, "org.example.App");
+    backend.dialogs();
+    let (response, _) = print(
+        &portal,
+        "org.example.App",
+        &document(dir.path()),
+        results.token,
+    );
+    assert_eq!(response, SUCCESS);
+    assert!(backend.dialogs().is_empty());
+    assert_eq!(fs::read(&output).unwrap(), b"%PDF-1.7");
+}
+
+#[test]
+fn print_without_token_shows_a_dialog() {
+    let backend = Arc::new(PrintToFile::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Print::new(backend.clone(), p.config())
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("out.pdf");
+    backend.push(Ok(print_to(&output)));
+    let (response, _) = print(&portal, "org.example.App", &document(dir.path()), None);
+    assert_eq!(response, SUCCESS);
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(ui.setup, PrintSetup::default());
+    assert_eq!(fs::read(&output).unwrap(), b"%PDF-1.7");
+}
+
+#[test]
+fn tokens_are_single_use_and_bound_to_the_app() {
+    let backend = Arc::new(PrintToFile::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Print::new(backend.clone(), p.config())
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let document = document(dir.path());
+    backend.push(Ok(print_to(&dir.path().join("out.pdf"))));
+    let (_, results) = prepare_print(&portal, "org.example.App");
+    backend.dialogs();
+    backend.push(Err(PrintError::Rejected));
+    let (response, _) = print(&portal, "org.example.Other", &document, results.token);
+    assert_eq!(response, CANCELLED);
+    assert_eq!(backend.dialogs().len(), 1);
+    let (response, _) = print(&portal, "org.example.App", &document, results.token);
+    assert_eq!(response, SUCCESS);
+    assert!(backend.dialogs().is_empty());
+    backend.push(Err(PrintError::Rejected));
+    let (response, _) = print(&portal, "org.example.App", &document, results.token);
+    assert_eq!(response, CANCELLED);
+    assert_eq!(backend.dialogs().len(), 1);
+}
+
+#[test]
+fn only_the_newest_tokens_are_kept() {
+    let backend = Arc::new(PrintToFile::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Print::new(backend.clone(), p.config())
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let document = document(dir.path());
+    let tokens: Vec<_> = (0..17)
+        .map(|_| {
+            backend.push(Ok(print_to(&dir.path().join("out.pdf"))));
+            prepare_print(&portal, "org.example.App").1.token
+        })
+        .collect();
+    backend.dialogs();
+    backend.push(Err(PrintError::Rejected));
+    let (response, _) = print(&portal, "org.example.App", &document, tokens[0]);
+    assert_eq!(response, CANCELLED);
+    assert_eq!(backend.dialogs().len(), 1);
+    let (response, _) = print(&portal, "org.example.App", &document, tokens[1]);
+    assert_eq!(response, SUCCESS);
+    assert!(backend.dialogs().is_empty());
+}
+
+#[test]
+fn close_cancels_prepare_print() {
+    let backend = Arc::new(PrintToFile::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Print::new(backend.clone(), p.config())
+    });
+    let handle = handle();
+    let pending = portal.spawn_call::<_, Response>(
+        PRINT,
+        "PreparePrint",
+        (
+            handle.clone(),
+            "org.example.App",
+            "",
+            "Report",
+            HashMap::<String, Value>::new(),
+            HashMap::<String, Value>::new(),
+            Options::new(),
+        ),
+    );
+    portal.close(&handle);
+    let (response, results) = pending.join().unwrap().unwrap();
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+    assert!(!portal.request_exists(&handle));
+}
@@ -0,0 +1,90 @@
+use {
+    crate::portal::{
+        request::run_request,
+        response::Response,
+        tests::harness::{handle, TestPortal, CANCELLED, OTHER, SUCCESS},
+    },
+    futures_util::poll,
+    std::{
+        sync::atomic::{AtomicBool, Ordering::Relaxed},
+        thread,
+        time::{Duration, Instant},
+    },
+    zbus::block_on,
+};
+
+#[test]
+fn dropped_requests_are_removed() {
+    let portal = TestPortal::new();
+    let conn = portal.connect();
+    let handle = handle();
+    let started = AtomicBool::new(false);
+    let mut request = Box::pin(run_request(conn.inner(), handle.clone(), |cancellation| {
+        started.store(true, Relaxed);
+        async move {
+            cancellation.cancelled().await;
+            Response::<()>::cancelled()
+        }
+    }));
+    while !started.load(Relaxed) {
+        assert!(block_on(async { poll!(request.as_mut()) }).is_pending());
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(portal.object_exists(&conn, &handle));
+    drop(request);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while portal.object_exists(&conn, &handle) {
+        assert!(Instant::now() < deadline, "the request was not removed");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn completed_requests_are_removed() {
+    let portal = TestPortal::new();
+    let conn = portal.connect();
+    let handle = handle();
+    let response = block_on(run_request(conn.inner(), handle.clone(), |_| async {
+        assert!(portal.object_exists(&conn, &handle));
+        Response::success(())
+    }));
+    assert_eq!(response.code(), SUCCESS);
+    assert!(!portal.object_exists(&conn, &handle));
+}
+
+/// `Close` may be called as soon as the request has been started. The request object
+/// must already exist at that point and the close must reach the future.
+#[test]
+fn close_right_after_the_request_starts_cancels_it() {
+    let portal = TestPortal::new();
+    let conn = portal.connect();
+    let handle = handle();
+    let response = block_on(run_request(conn.inner(), handle.clone(), |cancellation| {
+        portal.close_request(&conn, &handle).unwrap();
+        async move {
+            cancellation.cancelled().await;
+            Response::<()>::cancelled()
+        }
+    }));
+    assert_eq!(response.code(), CANCELLED);
+    assert!(!portal.object_exists(&conn, &handle));
+}
+
+#[test]
+fn duplicate_requests_fail() {
+    let portal = TestPortal::new();
+    let conn = portal.connect();
+    let handle = handle();
+    let response = block_on(run_request(conn.inner(), handle.clone(), |cancellation| {
+        let conn = conn.inner().clone();
+        let handle = handle.clone();
+        async move {
+            let response = run_request(&conn, handle, |_| async { Response::success(()) }).await;
+            assert_eq!(response.code(), OTHER);
+            assert!(!cancellation.is_cancelled());
+            Response::<()>::success(())
+        }
+    }));
+    assert_eq!(response.code(), SUCCESS);
+    assert!(!portal.object_exists(&conn, &handle));
+}
@@ -0,0 +1,72 @@
+use {
+    crate::{
+        config::Config,
+        gui::{
+            color_picker::{Color, ColorUi},
+            DialogError,
+        },
+        portal::{
+            portals::screenshot::Screenshot,
+            tests::{
+                backends::Scripted,
+                harness::{handle, Options, Response, TestPortal, CANCELLED, OTHER, SUCCESS},
+            },
+        },
+    },
+    std::sync::Arc,
+};
+
+const SCREENSHOT: &str = "org.freedesktop.impl.portal.Screenshot";
+
+fn call(portal: &TestPortal, method: &str) -> Response {
+    portal
+        .call(
+            SCREENSHOT,
+            method,
+            &(handle(), "org.example.Paint", "", Options::new()),
+        )
+        .unwrap()
+}
+
+#[test]
+fn picked_colors_are_returned() {
+    let backend = Arc::new(Scripted::<ColorUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Screenshot::new(backend.clone(), p.config())
+    });
+    backend.push(Ok(Color {
+        red: 1.0,
+        green: 0.5,
+        blue: 0.0,
+    }));
+    let (response, results) = call(&portal, "PickColor");
+    assert_eq!(response, SUCCESS);
+    let color = <(f64, f64, f64)>::try_from(results["color"].try_clone().unwrap()).unwrap();
+    assert_eq!(color, (1.0, 0.5, 0.0));
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(ui.app_id, "org.example.Paint");
+}
+
+#[test]
+fn rejected_colors_are_cancelled() {
+    let backend = Arc::new(Scripted::<ColorUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Screenshot::new(backend.clone(), p.config())
+    });
+    backend.push(Err(DialogError::Rejected));
+    let (response, results) = call(&portal, "PickColor");
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+}
+
+#[test]
+fn screenshots_fail() {
+    let backend = Arc::new(Scripted::<ColorUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| {
+        Screenshot::new(backend.clone(), p.config())
+    });
+    let (response, results) = call(&portal, "Screenshot");
+    assert_eq!(response, OTHER);
+    assert!(results.is_empty());
+    assert!(backend.dialogs().is_empty());
+}
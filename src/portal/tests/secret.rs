@@ -0,0 +1,379 @@
+use {
+    crate::portal::tests::harness::{handle, Options, Response, TestPortal, CANCELLED, SUCCESS},
+    std::{
+        collections::HashMap,
+        fs::File,
+        io::{Read, Seek},
+        os::fd::OwnedFd,
+        sync::{Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    },
+    zbus::{
+        blocking::Connection,
+        interface,
+        zvariant::{Fd, ObjectPath, OwnedObjectPath, OwnedValue, Value},
+        ObjectServer, SignalContext,
+    },
+};
+
+const SECRET: &str = "org.freedesktop.impl.portal.Secret";
+const SECRETS_NAME: &str = "org.freedesktop.secrets";
+const SECRETS_PATH: &str = "/org/freedesktop/secrets";
+const SESSION_PATH: &str = "/org/freedesktop/secrets/session/1";
+const COLLECTION_PATH: &str = "/org/freedesktop/secrets/collection/login";
+
+type ServiceSecret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+#[derive(Default)]
+struct Store {
+    /// The attributes and values of the items in the collection.
+    items: Vec<(HashMap<String, String>, Vec<u8>)>,
+    /// Whether the items are locked until they are unlocked through a prompt.
+    locked: bool,
+    /// Whether the `default` alias is unset until a collection is created.
+    no_default: bool,
+    /// Whether prompts are left open instead of completing.
+    ignore_prompts: bool,
+    prompts: usize,
+    dismissed_prompts: usize,
+    closed_sessions: usize,
+}
+
+fn item_path(index: usize) -> OwnedObjectPath {
+    OwnedObjectPath::try_from(format!("{COLLECTION_PATH}/{index}")).unwrap()
+}
+
+/// Exports a prompt that completes with `result`.
+async fn export_prompt(
+    server: &ObjectServer,
+    store: &Arc<Mutex<Store>>,
+    result: Value<'_>,
+) -> OwnedObjectPath {
+    let index = {
+        let mut store = store.lock().unwrap();
+        store.prompts += 1;
+        store.prompts
+    };
+    let path =
+        OwnedObjectPath::try_from(format!("/org/freedesktop/secrets/prompt/{index}")).unwrap();
+    let prompt = StubPrompt {
+        store: store.clone(),
+        result: result.try_into().unwrap(),
+    };
+    assert!(server.at(&path, prompt).await.unwrap());
+    path
+}
+
+struct StubService {
+    store: Arc<Mutex<Store>>,
+}
+
+#[interface(name = "org.freedesktop.Secret.Service")]
+impl StubService {
+    fn open_session(&self, algorithm: String, _input: OwnedValue) -> (OwnedValue, OwnedObjectPath) {
+        assert_eq!(algorithm, "plain");
+        let output = Value::new("").try_into().unwrap();
+        (output, OwnedObjectPath::try_from(SESSION_PATH).unwrap())
+    }
+
+    fn search_items(
+        &self,
+        attributes: HashMap<String, String>,
+    ) -> (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>) {
+        let store = self.store.lock().unwrap();
+        let unlocked = (0..store.items.len())
+            .filter(|&i| {
+                attributes
+                    .iter()
+                    .all(|(k, v)| store.items[i].0.get(k) == Some(v))
+            })
+            .map(item_path)
+            .collect();
+        if store.locked {
+            return (vec![], unlocked);
+        }
+        (unlocked, vec![])
+    }
+
+    async fn unlock(
+        &self,
+        objects: Vec<OwnedObjectPath>,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> (Vec<OwnedObjectPath>, OwnedObjectPath) {
+        let prompt = export_prompt(server, &self.store, Value::new(objects)).await;
+        (vec![], prompt)
+    }
+
+    fn read_alias(&self, name: String) -> OwnedObjectPath {
+        assert_eq!(name, "default");
+        let path = if self.store.lock().unwrap().no_default {
+            "/"
+        } else {
+            COLLECTION_PATH
+        };
+        OwnedObjectPath::try_from(path).unwrap()
+    }
+
+    async fn create_collection(
+        &self,
+        properties: HashMap<String, OwnedValue>,
+        alias: String,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> (OwnedObjectPath, OwnedObjectPath) {
+        let label = &properties["org.freedesktop.Secret.Collection.Label"];
+        assert_eq!(
+            String::try_from(label.try_clone().unwrap()).unwrap(),
+            "Login"
+        );
+        assert_eq!(alias, "default");
+        let collection = ObjectPath::try_from(COLLECTION_PATH).unwrap();
+        let prompt = export_prompt(server, &self.store, Value::new(collection)).await;
+        (OwnedObjectPath::try_from("/").unwrap(), prompt)
+    }
+}
+
+struct StubPrompt {
+    store: Arc<Mutex<Store>>,
+    result: OwnedValue,
+}
+
+#[interface(name = "org.freedesktop.Secret.Prompt")]
+impl StubPrompt {
+    async fn prompt(&self, _window_id: String, #[zbus(signal_context)] ctxt: SignalContext<'_>) {
+        {
+            let mut store = self.store.lock().unwrap();
+            if store.ignore_prompts {
+                return;
+            }
+            store.locked = false;
+            store.no_default = false;
+        }
+        Self::completed(&ctxt, false, &self.result).await.unwrap();
+    }
+
+    fn dismiss(&self) {
+        self.store.lock().unwrap().dismissed_prompts += 1;
+    }
+
+    #[zbus(signal)]
+    async fn completed(
+        ctxt: &SignalContext<'_>,
+        dismissed: bool,
+        result: &Value<'_>,
+    ) -> zbus::Result<()>;
+}
+
+struct StubCollection {
+    store: Arc<Mutex<Store>>,
+}
+
+#[interface(name = "org.freedesktop.Secret.Collection")]
+impl StubCollection {
+    async fn create_item(
+        &self,
+        properties: HashMap<String, OwnedValue>,
+        secret: ServiceSecret,
+        _replace: bool,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> (OwnedObjectPath, OwnedObjectPath) {
+        let attributes = &properties["org.freedesktop.Secret.Item.Attributes"];
+        let attributes = HashMap::try_from(attributes.try_clone().unwrap()).unwrap();
+        let index = {
+            let mut store = self.store.lock().unwrap();
+            store.items.push((attributes, secret.2));
+            store.items.len() - 1
+        };
+        let path = item_path(index);
+        let item = StubItem {
+            store: self.store.clone(),
+            index,
+        };
+        server.at(&path, item).await.unwrap();
+        (path, OwnedObjectPath::try_from("/").unwrap())
+    }
+}
+
+struct StubItem {
+    store: Arc<Mutex<Store>>,
+    index: usize,
+}
+
+#[interface(name = "org.freedesktop.Secret.Item")]
+impl StubItem {
+    fn get_secret(&self, session: OwnedObjectPath) -> (ServiceSecret,) {
+        let value = self.store.lock().unwrap().items[self.index].1.clone();
+        let content_type = "application/octet-stream".to_string();
+        ((session, vec![], value, content_type),)
+    }
+}
+
+struct StubSession {
+    store: Arc<Mutex<Store>>,
+}
+
+#[interface(name = "org.freedesktop.Secret.Session")]
+impl StubSession {
+    fn close(&self) {
+        self.store.lock().unwrap().closed_sessions += 1;
+    }
+}
+
+struct Setup {
+    portal: TestPortal,
+    services: Connection,
+    store: Arc<Mutex<Store>>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let portal = TestPortal::new();
+        let services = portal.connect();
+        let store = Arc::new(Mutex::new(Store::default()));
+        let service = StubService {
+            store: store.clone(),
+        };
+        services.object_server().at(SECRETS_PATH, service).unwrap();
+        let collection = StubCollection {
+            store: store.clone(),
+        };
+        services
+            .object_server()
+            .at(COLLECTION_PATH, collection)
+            .unwrap();
+        let session = StubSession {
+            store: store.clone(),
+        };
+        services.object_server().at(SESSION_PATH, session).unwrap();
+        services.request_name(SECRETS_NAME).unwrap();
+        Self {
+            portal,
+            services,
+            store,
+        }
+    }
+
+    /// Adds an item with the secret of `app_id` to the collection.
+    fn store_item(&self, app_id: &str, value: &[u8]) {
+        let index = {
+            let mut store = self.store.lock().unwrap();
+            let attributes = HashMap::from([("app_id".to_string(), app_id.to_string())]);
+            store.items.push((attributes, value.to_vec()));
+            store.items.len() - 1
+        };
+        let item = StubItem {
+            store: self.store.clone(),
+            index,
+        };
+        self.services
+            .object_server()
+            .at(item_path(index), item)
+            .unwrap();
+    }
+
+    /// Retrieves the secret of `app_id` through the portal.
+    fn retrieve(&self, app_id: &str) -> Vec<u8> {
+        let mut file = tempfile::tempfile().unwrap();
+        let fd = OwnedFd::from(file.try_clone().unwrap());
+        let (response, _): Response = self
+            .portal
+            .call(
+                SECRET,
+                "RetrieveSecret",
+                &(handle(), app_id, Fd::from(fd), Options::new()),
+            )
+            .unwrap();
+        assert_eq!(response, SUCCESS);
+        read(&mut file)
+    }
+}
+
+fn read(file: &mut File) -> Vec<u8> {
+    let mut secret = vec![];
+    file.rewind().unwrap();
+    file.read_to_end(&mut secret).unwrap();
+    secret
+}
+
+#[test]
+fn secrets_are_created_once_per_app() {
+    let setup = Setup::new();
+    let secret = setup.retrieve("org.example.Chat");
+    assert_eq!(secret.len(), 64);
+    assert_eq!(setup.retrieve("org.example.Chat"), secret);
+    let other = setup.retrieve("org.example.Mail");
+    assert_ne!(other, secret);
+    let store = setup.store.lock().unwrap();
+    assert_eq!(store.items.len(), 2);
+    let (attributes, value) = &store.items[0];
+    let expected = HashMap::from([("app_id".to_string(), "org.example.Chat".to_string())]);
+    assert_eq!(*attributes, expected);
+    assert_eq!(*value, secret);
+    assert_eq!(store.closed_sessions, 3);
+}
+
+#[test]
+fn existing_secrets_are_returned() {
+    let setup = Setup::new();
+    setup.store_item("org.example.Chat", b"stored");
+    assert_eq!(setup.retrieve("org.example.Chat"), b"stored");
+    assert_eq!(setup.store.lock().unwrap().items.len(), 1);
+}
+
+#[test]
+fn locked_secrets_are_unlocked() {
+    let setup = Setup::new();
+    setup.store_item("org.example.Chat", b"stored");
+    setup.store.lock().unwrap().locked = true;
+    assert_eq!(setup.retrieve("org.example.Chat"), b"stored");
+    let store = setup.store.lock().unwrap();
+    assert_eq!(store.prompts, 1);
+    assert!(!store.locked);
+}
+
+#[test]
+fn default_collection_is_created() {
+    let setup = Setup::new();
+    setup.store.lock().unwrap().no_default = true;
+    let secret = setup.retrieve("org.example.Chat");
+    let store = setup.store.lock().unwrap();
+    assert_eq!(store.prompts, 1);
+    assert!(!store.no_default);
+    assert_eq!(store.items.len(), 1);
+    assert_eq!(store.items[0].1, secret);
+}
+
+#[test]
+fn cancelled_prompts_are_dismissed() {
+    let setup = Setup::new();
+    setup.store_item("org.example.Chat", b"stored");
+    {
+        let mut store = setup.store.lock().unwrap();
+        store.locked = true;
+        store.ignore_prompts = true;
+    }
+    let handle = handle();
+    let file = tempfile::tempfile().unwrap();
+    let pending = setup.portal.spawn_call::<_, Response>(
+        SECRET,
+        "RetrieveSecret",
+        (
+            handle.clone(),
+            "org.example.Chat",
+            Fd::from(OwnedFd::from(file)),
+            Options::new(),
+        ),
+    );
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while setup.store.lock().unwrap().prompts == 0 {
+        assert!(Instant::now() < deadline, "no prompt was shown");
+        thread::sleep(Duration::from_millis(10));
+    }
+    setup.portal.close(&handle);
+    let (response, results) = pending.join().unwrap().unwrap();
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+    let store = setup.store.lock().unwrap();
+    assert_eq!(store.dismissed_prompts, 1);
+    assert_eq!(store.closed_sessions, 1);
+}
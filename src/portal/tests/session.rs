@@ -0,0 +1,126 @@
+use {
+    crate::portal::{
+        session::Sessions,
+        tests::harness::{session_handle, TestPortal},
+        PATH,
+    },
+    std::{
+        thread,
+        time::{Duration, Instant},
+    },
+    zbus::{
+        blocking::{Connection, Proxy},
+        interface,
+        message::Header,
+        zvariant::OwnedObjectPath,
+    },
+};
+
+const BACKEND: &str = "org.example.SessionBackend";
+const SESSION: &str = "org.freedesktop.impl.portal.Session";
+
+/// A session-based interface whose sessions store a string.
+struct Backend {
+    sessions: Sessions<String>,
+}
+
+#[interface(name = "org.example.SessionBackend")]
+impl Backend {
+    async fn create(
+        &self,
+        #[zbus(connection)] conn: &zbus::Connection,
+        #[zbus(header)] header: Header<'_>,
+        handle: OwnedObjectPath,
+        state: String,
+    ) -> zbus::fdo::Result<()> {
+        let peer = header.sender().cloned();
+        self.sessions
+            .create(conn, handle, peer, state)
+            .await
+            .map(drop)
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Returns the state of a session or an empty string if it is closed.
+    fn state(&self, handle: OwnedObjectPath) -> String {
+        self.sessions
+            .with(&handle, |s| s.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn setup() -> TestPortal {
+    let portal = TestPortal::new();
+    portal.serve(Backend {
+        sessions: Sessions::default(),
+    });
+    portal
+}
+
+fn create(portal: &TestPortal, client: &Connection, handle: &OwnedObjectPath) {
+    client
+        .call_method(
+            Some(portal.name()),
+            PATH,
+            Some(BACKEND),
+            "Create",
+            &(handle, "state"),
+        )
+        .unwrap();
+}
+
+fn state(portal: &TestPortal, handle: &OwnedObjectPath) -> String {
+    portal.call(BACKEND, "State", &(handle,)).unwrap()
+}
+
+#[test]
+fn closing_a_session_drops_its_state() {
+    let portal = setup();
+    let handle = session_handle();
+    let client = portal.connect();
+    create(&portal, &client, &handle);
+    assert_eq!(state(&portal, &handle), "state");
+    let session = Proxy::new(&portal.connect(), portal.name(), &handle, SESSION).unwrap();
+    assert_eq!(session.get_property::<u32>("version").unwrap(), 1);
+    portal.close_session(&handle).unwrap();
+    assert_eq!(state(&portal, &handle), "");
+    assert!(portal.close_session(&handle).is_err());
+}
+
+#[test]
+fn sessions_cannot_be_created_twice() {
+    let portal = setup();
+    let handle = session_handle();
+    let client = portal.connect();
+    create(&portal, &client, &handle);
+    let res = client.call_method(
+        Some(portal.name()),
+        PATH,
+        Some(BACKEND),
+        "Create",
+        &(&handle, "other"),
+    );
+    assert!(res.is_err());
+    assert_eq!(state(&portal, &handle), "state");
+}
+
+#[test]
+fn sessions_are_closed_when_the_peer_disconnects() {
+    let portal = setup();
+    let handle = session_handle();
+    let other = session_handle();
+    let (client, other_client) = (portal.connect(), portal.connect());
+    create(&portal, &client, &handle);
+    create(&portal, &other_client, &other);
+    let session = Proxy::new(&portal.connect(), portal.name(), &handle, SESSION).unwrap();
+    let mut closed = session.receive_signal("Closed").unwrap();
+    drop(client);
+    assert!(closed.next().is_some());
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !state(&portal, &handle).is_empty() {
+        assert!(Instant::now() < deadline, "the session was not closed");
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(portal.close_session(&handle).is_err());
+    assert_eq!(state(&portal, &other), "state");
+}
@@ -0,0 +1,151 @@
+use {
+    crate::{gui::settings::Namespaces, portal::tests::harness::TestPortal},
+    async_channel::Sender,
+    gtk4::glib::{KeyFile, KeyFileFlags},
+    std::collections::HashMap,
+    zbus::zvariant::{OwnedValue, Structure, Value},
+};
+
+const SETTINGS: &str = "org.freedesktop.impl.portal.Settings";
+const APPEARANCE: &str = "org.freedesktop.appearance";
+const INTERFACE: &str = "org.gnome.desktop.interface";
+
+type ReadAllResult = HashMap<String, HashMap<String, OwnedValue>>;
+
+/// Creates a portal whose settings are sent through the returned channel.
+fn portal() -> (TestPortal, Sender<Namespaces>) {
+    let (send, recv) = async_channel::unbounded();
+    (TestPortal::builder().settings(recv).build(), send)
+}
+
+fn namespaces(color_scheme: u32) -> Namespaces {
+    let appearance = HashMap::from([
+        ("color-scheme".to_string(), OwnedValue::from(color_scheme)),
+        ("contrast".to_string(), OwnedValue::from(0u32)),
+    ]);
+    let interface = HashMap::from([(
+        "gtk-theme".to_string(),
+        Value::from("Adwaita").try_to_owned().unwrap(),
+    )]);
+    Namespaces::from([
+        (APPEARANCE.to_string(), appearance),
+        (INTERFACE.to_string(), interface),
+    ])
+}
+
+fn read_all(portal: &TestPortal, namespaces: &[&str]) -> ReadAllResult {
+    portal.call(SETTINGS, "ReadAll", &(namespaces,)).unwrap()
+}
+
+#[test]
+fn read_all_filters_namespaces() {
+    let (portal, settings) = portal();
+    settings.try_send(namespaces(1)).unwrap();
+    let all = read_all(&portal, &[]);
+    assert_eq!(all.len(), 2);
+    assert_eq!(u32::try_from(&all[APPEARANCE]["color-scheme"]).unwrap(), 1);
+    let appearance = read_all(&portal, &[APPEARANCE]);
+    assert_eq!(appearance.keys().collect::<Vec<_>>(), [APPEARANCE]);
+    let gnome = read_all(&portal, &["org.gnome.*"]);
+    assert_eq!(gnome.keys().collect::<Vec<_>>(), [INTERFACE]);
+    assert!(read_all(&portal, &["org.kde.kdeglobals"]).is_empty());
+}
+
+#[test]
+fn read_returns_values_or_not_found() {
+    let (portal, settings) = portal();
+    settings.try_send(namespaces(0)).unwrap();
+    let theme: OwnedValue = portal
+        .call(SETTINGS, "Read", &(INTERFACE, "gtk-theme"))
+        .unwrap();
+    assert_eq!(<&str>::try_from(&theme).unwrap(), "Adwaita");
+    let err = portal
+        .call::<_, OwnedValue>(SETTINGS, "Read", &(INTERFACE, "font-name"))
+        .unwrap_err();
+    let zbus::Error::MethodError(name, _, _) = err else {
+        panic!("unexpected error {err}");
+    };
+    assert_eq!(name.as_str(), "org.freedesktop.portal.Error.NotFound");
+}
+
+#[test]
+fn calls_wait_for_the_initial_settings() {
+    let (portal, settings) = portal();
+    let pending = portal.spawn_call::<_, ReadAllResult>(SETTINGS, "ReadAll", (vec![APPEARANCE],));
+    settings.try_send(namespaces(1)).unwrap();
+    assert_eq!(pending.join().unwrap().unwrap().len(), 1);
+}
+
+#[test]
+fn changes_are_signaled() {
+    let (portal, settings) = portal();
+    let proxy = portal.proxy(SETTINGS);
+    assert_eq!(proxy.get_property::<u32>("version").unwrap(), 2);
+    let mut changes = proxy.receive_signal("SettingChanged").unwrap();
+    settings.try_send(namespaces(0)).unwrap();
+    read_all(&portal, &[]);
+    settings.try_send(namespaces(1)).unwrap();
+    let change = changes.next().unwrap();
+    let (namespace, key, value): (String, String, OwnedValue) =
+        change.body().deserialize().unwrap();
+    assert_eq!(
+        (namespace.as_str(), key.as_str()),
+        (APPEARANCE, "color-scheme")
+    );
+    assert_eq!(u32::try_from(&value).unwrap(), 1);
+    let all = read_all(&portal, &[APPEARANCE]);
+    assert_eq!(u32::try_from(&all[APPEARANCE]["color-scheme"]).unwrap(), 1);
+}
+
+#[test]
+fn removals_are_signaled() {
+    let (portal, settings) = portal();
+    let proxy = portal.proxy(SETTINGS);
+    let mut changes = proxy.receive_signal("SettingChanged").unwrap();
+    let mut old = namespaces(0);
+    let accent = Value::from((0.25, 0.5, 1.0));
+    old.get_mut(APPEARANCE)
+        .unwrap()
+        .insert("accent-color".to_string(), accent.try_to_owned().unwrap());
+    settings.try_send(old).unwrap();
+    read_all(&portal, &[]);
+    settings.try_send(namespaces(0)).unwrap();
+    let change = changes.next().unwrap();
+    let (namespace, key, value): (String, String, OwnedValue) =
+        change.body().deserialize().unwrap();
+    assert_eq!(
+        (namespace.as_str(), key.as_str()),
+        (APPEARANCE, "accent-color")
+    );
+    let accent = Structure::try_from(value).unwrap();
+    assert_eq!(
+        accent.fields(),
+        [Value::F64(-1.0), Value::F64(-1.0), Value::F64(-1.0)]
+    );
+    assert!(!read_all(&portal, &[APPEARANCE])[APPEARANCE].contains_key("accent-color"));
+}
+
+#[test]
+fn key_file_values_are_parsed() {
+    let file = KeyFile::new();
+    let data = "[org.freedesktop.appearance]\n\
+                color-scheme=uint32 2\n\
+                accent-color=(0.25, 0.5, 1.0)\n\
+                contrast=not a variant\n\
+                [org.gnome.desktop.interface]\n\
+                font-name='Cantarell 11'\n\
+                cursor-size=24\n";
+    file.load_from_data(data, KeyFileFlags::NONE).unwrap();
+    let namespaces = crate::gui::settings::parse(&file);
+    let appearance = &namespaces[APPEARANCE];
+    assert_eq!(appearance.len(), 2);
+    assert_eq!(u32::try_from(&appearance["color-scheme"]).unwrap(), 2);
+    let accent = Structure::try_from(appearance["accent-color"].try_clone().unwrap()).unwrap();
+    assert_eq!(accent.signature().as_str(), "(ddd)");
+    let interface = &namespaces[INTERFACE];
+    assert_eq!(
+        <&str>::try_from(&interface["font-name"]).unwrap(),
+        "Cantarell 11"
+    );
+    assert_eq!(i32::try_from(&interface["cursor-size"]).unwrap(), 24);
+}
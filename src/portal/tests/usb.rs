@@ -0,0 +1,130 @@
+use {
+    crate::{
+        config::Config,
+        gui::{
+            usb::{UsbDevice, UsbUi},
+            DialogError,
+        },
+        portal::{
+            portals::usb::Usb,
+            tests::{
+                backends::Scripted,
+                harness::{handle, Options, Response, TestPortal, CANCELLED, SUCCESS},
+            },
+        },
+    },
+    std::{collections::HashMap, sync::Arc},
+    zbus::zvariant::{OwnedValue, Value},
+};
+
+const USB: &str = "org.freedesktop.impl.portal.Usb";
+
+type Device = (&'static str, Options, Options);
+
+fn device(id: &'static str, properties: &[(&'static str, &'static str)], writable: bool) -> Device {
+    let properties: HashMap<_, _> = properties
+        .iter()
+        .map(|&(k, v)| (k, Value::new(v)))
+        .collect();
+    let info = Options::from([
+        ("device-file", Value::new(format!("/dev/bus/usb/001/{id}"))),
+        ("properties", Value::new(properties)),
+    ]);
+    (
+        id,
+        info,
+        Options::from([("writable", Value::new(writable))]),
+    )
+}
+
+fn devices() -> Vec<Device> {
+    vec![
+        device(
+            "001",
+            &[
+                ("ID_VENDOR_FROM_DATABASE", "Example Corp."),
+                ("ID_MODEL_FROM_DATABASE", "Keyboard"),
+                ("ID_SERIAL_SHORT", "1234"),
+            ],
+            false,
+        ),
+        device(
+            "002",
+            &[("ID_VENDOR", "Example_Inc."), ("ID_MODEL", "Flash_Drive")],
+            true,
+        ),
+        device("003", &[], false),
+    ]
+}
+
+fn acquire_devices(portal: &TestPortal, devices: Vec<Device>) -> Response {
+    portal
+        .call(
+            USB,
+            "AcquireDevices",
+            &(handle(), "", "org.example.Flasher", devices, Options::new()),
+        )
+        .unwrap()
+}
+
+#[test]
+fn devices_are_described_from_udev_properties() {
+    let backend = Arc::new(Scripted::<UsbUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| Usb::new(backend.clone(), p.config()));
+    backend.push(Err(DialogError::Rejected));
+    let (response, results) = acquire_devices(&portal, devices());
+    assert_eq!(response, CANCELLED);
+    assert!(results.is_empty());
+    let ui = backend.dialogs().pop().unwrap();
+    assert_eq!(ui.app_id, "org.example.Flasher");
+    let expected = [
+        UsbDevice {
+            id: "001".to_string(),
+            vendor: Some("Example Corp.".to_string()),
+            product: Some("Keyboard".to_string()),
+            serial: Some("1234".to_string()),
+            writable: false,
+        },
+        UsbDevice {
+            id: "002".to_string(),
+            vendor: Some("Example Inc.".to_string()),
+            product: Some("Flash Drive".to_string()),
+            serial: None,
+            writable: true,
+        },
+        UsbDevice {
+            id: "003".to_string(),
+            vendor: None,
+            product: None,
+            serial: None,
+            writable: false,
+        },
+    ];
+    assert_eq!(ui.devices, expected);
+}
+
+#[test]
+fn only_allowed_devices_are_returned() {
+    let backend = Arc::new(Scripted::<UsbUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| Usb::new(backend.clone(), p.config()));
+    backend.push(Ok(vec!["002".to_string(), "unknown".to_string()]));
+    let (response, results) = acquire_devices(&portal, devices());
+    assert_eq!(response, SUCCESS);
+    let devices = Vec::<(String, HashMap<String, OwnedValue>)>::try_from(
+        results["devices"].try_clone().unwrap(),
+    )
+    .unwrap();
+    assert_eq!(devices.len(), 1);
+    let (id, access) = &devices[0];
+    assert_eq!(id, "002");
+    assert!(bool::try_from(&access["writable"]).unwrap());
+}
+
+#[test]
+fn no_dialog_is_shown_without_devices() {
+    let backend = Arc::new(Scripted::<UsbUi>::default());
+    let portal = TestPortal::with(Config::default(), |p| Usb::new(backend.clone(), p.config()));
+    let (response, _) = acquire_devices(&portal, vec![]);
+    assert_eq!(response, SUCCESS);
+    assert!(backend.dialogs().is_empty());
+}
@@ -0,0 +1,130 @@
+use {
+    crate::{
+        config::Config,
+        gui::{wallpaper::SetOn, DialogError},
+        portal::{
+            portals::wallpaper::Wallpaper,
+            tests::{
+                backends::FakeWallpaper,
+                harness::{handle, Options, TestPortal, CANCELLED, OTHER, SUCCESS},
+            },
+        },
+        utils::wallpaper::Target,
+    },
+    std::{ffi::OsString, path::Path, sync::Arc},
+    zbus::zvariant::Value,
+};
+
+const WALLPAPER: &str = "org.freedesktop.impl.portal.Wallpaper";
+
+fn args(args: &[&str]) -> Vec<OsString> {
+    args.iter().map(OsString::from).collect()
+}
+
+fn config() -> Config {
+    let mut config = Config::default();
+    config.wallpaper.background_command = Some(args(&["swww", "img", "%f"]));
+    config.wallpaper.lockscreen_command = Some(args(&["lock", "--image=%f"]));
+    config
+}
+
+fn set_wallpaper(portal: &TestPortal, uri: &str, options: Options) -> u32 {
+    portal
+        .call(
+            WALLPAPER,
+            "SetWallpaperURI",
+            &(handle(), "org.example.Photos", "", uri, options),
+        )
+        .unwrap()
+}
+
+#[test]
+fn commands_are_run_with_the_path() {
+    let backend = Arc::new(FakeWallpaper::default());
+    let portal = TestPortal::with(config(), |p| {
+        Wallpaper::new(backend.clone(), backend.clone(), p.config())
+    });
+    let response = set_wallpaper(&portal, "file:///tmp/a.png", Options::new());
+    assert_eq!(response, SUCCESS);
+    assert!(backend.previews.dialogs().is_empty());
+    assert_eq!(
+        *backend.commands.lock().unwrap(),
+        [
+            (Target::Background, args(&["swww", "img", "/tmp/a.png"])),
+            (Target::Lockscreen, args(&["lock", "--image=/tmp/a.png"])),
+        ],
+    );
+}
+
+#[test]
+fn rejected_previews_are_cancelled() {
+    let backend = Arc::new(FakeWallpaper::default());
+    let portal = TestPortal::with(config(), |p| {
+        Wallpaper::new(backend.clone(), backend.clone(), p.config())
+    });
+    backend.previews.push(Err(DialogError::Rejected));
+    let options = Options::from([
+        ("show-preview", Value::new(true)),
+        ("set-on", Value::new("background")),
+    ]);
+    let response = set_wallpaper(&portal, "file:///tmp/a.png", options);
+    assert_eq!(response, CANCELLED);
+    let ui = backend.previews.dialogs().pop().unwrap();
+    assert_eq!(ui.path, Path::new("/tmp/a.png"));
+    assert_eq!(ui.set_on, SetOn::Background);
+    assert!(backend.commands.lock().unwrap().is_empty());
+}
+
+#[test]
+fn missing_commands_fail() {
+    let backend = Arc::new(FakeWallpaper::default());
+    let mut background_only = config();
+    background_only.wallpaper.lockscreen_command = None;
+    let options = Options::from([("set-on", Value::new("lockscreen"))]);
+    let background_only = TestPortal::with(background_only, |p| {
+        Wallpaper::new(backend.clone(), backend.clone(), p.config())
+    });
+    let response = set_wallpaper(&background_only, "file:///tmp/a.png", options);
+    assert_eq!(response, OTHER);
+    assert!(backend.commands.lock().unwrap().is_empty());
+    let unconfigured = TestPortal::with(Config::default(), |p| {
+        Wallpaper::new(backend.clone(), backend.clone(), p.config())
+    });
+    let response = set_wallpaper(&unconfigured, "file:///tmp/a.png", Options::new());
+    assert_eq!(response, OTHER);
+    assert!(backend.commands.lock().unwrap().is_empty());
+}
+
+#[test]
+fn both_sets_the_configured_targets() {
+    let backend = Arc::new(FakeWallpaper::default());
+    let mut config = config();
+    config.wallpaper.lockscreen_command = None;
+    let portal = TestPortal::with(config, |p| {
+        Wallpaper::new(backend.clone(), backend.clone(), p.config())
+    });
+    let response = set_wallpaper(&portal, "file:///tmp/a.png", Options::new());
+    assert_eq!(response, SUCCESS);
+    let options = Options::from([("set-on", Value::new("both"))]);
+    let response = set_wallpaper(&portal, "file:///tmp/b.png", options);
+    assert_eq!(response, SUCCESS);
+    let targets: Vec<_> = backend
+        .commands
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|c| c.0)
+        .collect();
+    assert_eq!(targets, [Target::Background, Target::Background]);
+}
+
+#[test]
+fn remote_uris_fail() {
+    let backend = Arc::new(FakeWallpaper::default());
+    let portal = TestPortal::with(config(), |p| {
+        Wallpaper::new(backend.clone(), backend.clone(), p.config())
+    });
+    let response = set_wallpaper(&portal, "https://example.org/a.png", Options::new());
+    assert_eq!(response, OTHER);
+    assert!(backend.commands.lock().unwrap().is_empty());
+}
@@ -1 +1,5 @@
+pub mod cancellation;
+#[cfg(not(feature = "file-chooser-window"))]
 pub mod file_chooser_ext;
+pub mod icon;
+pub mod wallpaper;
@@ -0,0 +1,44 @@
+use async_channel::{Receiver, Sender};
+
+/// Creates a connected pair of [`CancellationSource`] and [`Cancellation`].
+pub fn cancellation() -> (CancellationSource, Cancellation) {
+    let (send, recv) = async_channel::bounded(1);
+    (CancellationSource { send }, Cancellation { recv })
+}
+
+/// The sending half of a cancellation.
+///
+/// Dropping the source also cancels all connected [`Cancellation`]s. It is deliberately
+/// not `Clone`: hand out clones of the [`Cancellation`] instead.
+pub struct CancellationSource {
+    send: Sender<()>,
+}
+
+impl CancellationSource {
+    pub fn cancel(&self) {
+        self.send.close();
+    }
+}
+
+impl Drop for CancellationSource {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// The receiving half of a cancellation.
+#[derive(Clone)]
+pub struct Cancellation {
+    recv: Receiver<()>,
+}
+
+impl Cancellation {
+    pub fn is_cancelled(&self) -> bool {
+        self.recv.is_closed()
+    }
+
+    /// Completes once the operation has been cancelled.
+    pub async fn cancelled(&self) {
+        let _ = self.recv.recv().await;
+    }
+}
@@ -0,0 +1,42 @@
+use {
+    std::path::PathBuf,
+    url::Url,
+    zbus::zvariant::{OwnedValue, Structure, Value},
+};
+
+/// A `GIcon` as serialized by `g_icon_serialize`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Icon {
+    Themed(Vec<String>),
+    File(PathBuf),
+    /// An encoded image, e.g. a PNG.
+    Bytes(Vec<u8>),
+}
+
+impl Icon {
+    /// Parses a `GIcon` serialized as `(sv)`.
+    ///
+    /// Themed, file and bytes icons are supported.
+    pub fn parse(icon: &OwnedValue) -> Option<Self> {
+        let fields = Structure::try_from(icon.try_clone().ok()?)
+            .ok()?
+            .into_fields();
+        let [kind, Value::Value(value)] = <[Value; 2]>::try_from(fields).ok()? else {
+            return None;
+        };
+        let kind: String = kind.try_into().ok()?;
+        let icon = match kind.as_str() {
+            "themed" => Self::Themed((*value).try_into().ok()?),
+            "file" => {
+                let uri: String = (*value).try_into().ok()?;
+                Self::File(Url::parse(&uri).ok()?.to_file_path().ok()?)
+            }
+            "bytes" => Self::Bytes((*value).try_into().ok()?),
+            _ => {
+                log::debug!("Ignoring unsupported icon of type {kind}");
+                return None;
+            }
+        };
+        Some(icon)
+    }
+}
@@ -0,0 +1,100 @@
+use {
+    std::{
+        collections::HashMap,
+        ffi::OsString,
+        io,
+        process::{Child, Command, Stdio},
+        sync::Mutex,
+    },
+    thiserror::Error,
+};
+
+#[derive(Debug, Error)]
+pub enum WallpaperError {
+    #[error("The wallpaper command is empty")]
+    EmptyCommand,
+    #[error("Could not spawn the wallpaper command")]
+    Spawn(#[source] io::Error),
+}
+
+/// The surface whose wallpaper is set by a single command.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Target {
+    Background,
+    Lockscreen,
+}
+
+/// Runs the commands that set the wallpaper.
+pub trait WallpaperSetter: Send + Sync {
+    /// Runs the command that sets the wallpaper of `target`.
+    ///
+    /// This returns once the command has been started.
+    fn set(&self, target: Target, command: Vec<OsString>) -> Result<(), WallpaperError>;
+}
+
+/// Spawns the wallpaper commands without waiting for them.
+///
+/// Some setters, e.g. `swaybg`, keep running until they are killed, so the last setter of
+/// each target is kept and killed before the next one is spawned.
+#[derive(Default)]
+pub struct Setters {
+    children: Mutex<HashMap<Target, Child>>,
+}
+
+impl WallpaperSetter for Setters {
+    fn set(&self, target: Target, command: Vec<OsString>) -> Result<(), WallpaperError> {
+        let (program, args) = command.split_first().ok_or(WallpaperError::EmptyCommand)?;
+        let mut children = self.children.lock().unwrap();
+        if let Some(mut previous) = children.remove(&target) {
+            // the setter has not been reaped, so this cannot hit a reused pid
+            let _ = previous.kill();
+            let _ = previous.wait();
+        }
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .spawn()
+            .map_err(WallpaperError::Spawn)?;
+        children.insert(target, child);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sleep() -> Vec<OsString> {
+        vec!["sleep".into(), "60".into()]
+    }
+
+    fn is_running(pid: u32) -> bool {
+        unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+    }
+
+    #[test]
+    fn previous_setters_are_killed() {
+        let setters = Setters::default();
+        setters.set(Target::Background, sleep()).unwrap();
+        setters.set(Target::Lockscreen, sleep()).unwrap();
+        let pid = |target| setters.children.lock().unwrap()[&target].id();
+        let background = pid(Target::Background);
+        let lockscreen = pid(Target::Lockscreen);
+        setters.set(Target::Background, sleep()).unwrap();
+        assert!(!is_running(background));
+        assert!(is_running(lockscreen));
+        assert!(is_running(pid(Target::Background)));
+        for (_, mut child) in setters.children.lock().unwrap().drain() {
+            child.kill().unwrap();
+            child.wait().unwrap();
+        }
+    }
+
+    #[test]
+    fn empty_commands_fail() {
+        let setters = Setters::default();
+        let res = setters.set(Target::Background, vec![]);
+        assert!(matches!(res, Err(WallpaperError::EmptyCommand)));
+        assert!(setters.children.lock().unwrap().is_empty());
+    }
+}